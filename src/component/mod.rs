@@ -1,7 +1,17 @@
 use std::thread;
 use std::sync::mpsc::{channel, Receiver};
-use crate::rekordbox::{Server, Database, Event};
-use std::path::Path;
+use crate::rekordbox::{
+    AnalysisCache,
+    Server,
+    Database,
+    DeviceOptions,
+    Event,
+    History,
+    default_analysis_folder,
+    default_database_file,
+    default_history_folder,
+};
+use crate::library::{LibraryRoot, ScanOptions};
 
 pub struct App {
     rekordbox_server: Server,
@@ -9,12 +19,15 @@ pub struct App {
 }
 
 impl App {
-    pub fn new<T: AsRef<Path>>(path: T) -> Self {
+    pub fn new(roots: Vec<LibraryRoot>, options: ScanOptions, device: DeviceOptions) -> Self {
         let (tx, rx) = channel::<Event>();
-        let database = Database::new(path);
+        let database = Database::open(roots, &options, default_database_file())
+            .with_history(History::load(default_history_folder()))
+            .with_analysis_cache(AnalysisCache::new(default_analysis_folder()));
 
         let rekordbox_server = Server::new(
             database,
+            device,
             tx,
         );
 
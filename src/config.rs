@@ -0,0 +1,255 @@
+use serde::Deserialize;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::library::{LibraryRoot, ScanOptions};
+use crate::rekordbox::DeviceOptions;
+use crate::utils::config_folder;
+
+const USAGE: &str = "usage: termdj [--config FILE] [--library PATH [--include GLOB]... [--exclude GLOB]...]... \
+[--name NAME] [--player-number N] [--interface IFACE] [--detect-keys]";
+
+/// Settings read from `config.toml`, for example:
+///
+/// ```toml
+/// name = "Term DJ"
+/// player_number = 17
+/// interface = "eth0"
+/// detect_keys = false
+///
+/// [[library]]
+/// path = "/home/dj/Music"
+/// include = ["Techno/**"]
+/// exclude = ["**/*.wav"]
+/// ```
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub name: Option<String>,
+    pub player_number: Option<u8>,
+    pub interface: Option<String>,
+    pub detect_keys: bool,
+    #[serde(rename = "library")]
+    pub libraries: Vec<LibraryConfig>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LibraryConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl LibraryConfig {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            include: vec![],
+            exclude: vec![],
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Glob(PathBuf, globset::Error),
+    Argument(String),
+    NoLibrary,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::Read(file, err) => write!(f, "Failed reading {}: {}", file.display(), err),
+            ConfigError::Parse(file, err) => write!(f, "Invalid config in {}: {}", file.display(), err),
+            ConfigError::Glob(path, err) => write!(f, "Invalid glob for library {}: {}", path.display(), err),
+            ConfigError::Argument(message) => write!(f, "{}\n{}", message, USAGE),
+            ConfigError::NoLibrary => write!(
+                f,
+                "No library configured, add a [[library]] to the config file or pass --library PATH\n{}",
+                USAGE,
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// `config.toml` in the TermDJ config folder.
+pub fn default_config_file() -> Option<PathBuf> {
+    Some(config_folder()?.join("config.toml"))
+}
+
+impl Config {
+    pub fn load(file: &Path) -> Result<Self, ConfigError> {
+        let contents = std::fs::read_to_string(file)
+            .map_err(|err| ConfigError::Read(file.to_path_buf(), err))?;
+
+        toml::from_str(&contents).map_err(|err| ConfigError::Parse(file.to_path_buf(), err))
+    }
+
+    /// Load the file given by `--config`, or the default one when it exists,
+    /// and apply the remaining command line arguments on top of it.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ConfigError> {
+        let args: Vec<String> = args.into_iter().collect();
+
+        let config = match args.iter().position(|arg| arg == "--config") {
+            Some(index) => match args.get(index + 1) {
+                Some(file) => Config::load(Path::new(file))?,
+                None => return Err(ConfigError::Argument("--config requires a value".to_string())),
+            },
+            None => match default_config_file() {
+                Some(file) if file.exists() => Config::load(&file)?,
+                _ => Config::default(),
+            },
+        };
+
+        config.with_args(args)
+    }
+
+    /// Command line arguments override the config file, libraries given on the
+    /// command line replace the configured ones and globs apply to the
+    /// `--library` preceding them.
+    fn with_args(mut self, args: Vec<String>) -> Result<Self, ConfigError> {
+        let mut libraries: Vec<LibraryConfig> = vec![];
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let mut value = || args.next()
+                .ok_or_else(|| ConfigError::Argument(format!("{} requires a value", arg)));
+
+            match arg.as_str() {
+                "--config" => { value()?; },
+                "--library" => libraries.push(LibraryConfig::new(PathBuf::from(value()?))),
+                "--include" | "--exclude" => {
+                    let glob = value()?;
+                    let library = libraries.last_mut().ok_or_else(|| {
+                        ConfigError::Argument(format!("{} has to follow a --library", arg))
+                    })?;
+
+                    if arg == "--include" {
+                        library.include.push(glob);
+                    } else {
+                        library.exclude.push(glob);
+                    }
+                },
+                "--name" => self.name = Some(value()?),
+                "--player-number" => {
+                    let number = value()?;
+                    self.player_number = Some(number.parse().map_err(|_| {
+                        ConfigError::Argument(format!("Invalid player number {}", number))
+                    })?);
+                },
+                "--interface" => self.interface = Some(value()?),
+                "--detect-keys" => self.detect_keys = true,
+                _ => return Err(ConfigError::Argument(format!("Unknown argument {}", arg))),
+            };
+        }
+
+        if !libraries.is_empty() {
+            self.libraries = libraries;
+        }
+
+        Ok(self)
+    }
+
+    pub fn roots(&self) -> Result<Vec<LibraryRoot>, ConfigError> {
+        if self.libraries.is_empty() {
+            return Err(ConfigError::NoLibrary);
+        }
+
+        self.libraries.iter()
+            .map(|library| {
+                LibraryRoot::with_globs(&library.path, &library.include, &library.exclude)
+                    .map_err(|err| ConfigError::Glob(library.path.clone(), err))
+            })
+            .collect()
+    }
+
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            detect_keys: self.detect_keys,
+        }
+    }
+
+    pub fn device(&self) -> DeviceOptions {
+        let defaults = DeviceOptions::default();
+
+        DeviceOptions {
+            name: self.name.clone().unwrap_or(defaults.name),
+            player_number: self.player_number.unwrap_or(defaults.player_number),
+            interface: self.interface.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn it_reads_libraries_and_device_settings() {
+        let config: Config = toml::from_str(r#"
+            name = "Booth"
+            player_number = 18
+            interface = "eth0"
+
+            [[library]]
+            path = "/music/techno"
+            exclude = ["**/*.wav"]
+
+            [[library]]
+            path = "/music/house"
+        "#).unwrap();
+
+        assert_eq!(config.libraries.len(), 2);
+        assert_eq!(config.libraries[0].exclude, vec!["**/*.wav".to_string()]);
+        assert_eq!(config.roots().unwrap()[1].path, PathBuf::from("/music/house"));
+
+        let device = config.device();
+        assert_eq!(device.name, "Booth");
+        assert_eq!(device.player_number, 18);
+        assert_eq!(device.interface, Some("eth0".to_string()));
+    }
+
+    #[test]
+    fn it_overrides_the_config_file_with_arguments() {
+        let config = Config {
+            name: Some("Booth".to_string()),
+            libraries: vec![LibraryConfig::new(PathBuf::from("/music/techno"))],
+            ..Config::default()
+        }.with_args(args(&[
+            "--library", "/music/house", "--include", "Sets/**",
+            "--player-number", "19",
+            "--detect-keys",
+        ])).unwrap();
+
+        assert_eq!(config.libraries, vec![LibraryConfig {
+            path: PathBuf::from("/music/house"),
+            include: vec!["Sets/**".to_string()],
+            exclude: vec![],
+        }]);
+        assert_eq!(config.device().name, "Booth");
+        assert_eq!(config.device().player_number, 19);
+        assert!(config.scan_options().detect_keys);
+    }
+
+    #[test]
+    fn it_rejects_invalid_arguments() {
+        assert!(Config::default().with_args(args(&["--include", "*.mp3"])).is_err());
+        assert!(Config::default().with_args(args(&["--player-number", "x"])).is_err());
+        assert!(Config::default().with_args(args(&["--name"])).is_err());
+        assert!(Config::default().with_args(args(&["--verbose"])).is_err());
+        assert!(matches!(Config::default().roots(), Err(ConfigError::NoLibrary)));
+    }
+}
@@ -1,15 +1,125 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use walkdir::{DirEntry, WalkDir};
 use std::path::{Path, PathBuf};
-use id3::{Tag, v1 as id3v1};
+use id3::{Tag, v1 as id3v1, frame::PictureType};
 use std::fs::{File, metadata};
 use std::os::unix::fs::MetadataExt;
-use std::ffi::OsStr;
 use std::io;
+use std::time::{Duration, SystemTime};
 use crate::rekordbox::{
+    Artwork,
+    Color,
     Metadata,
     MetadataTrack as Track,
 };
 
+mod analysis;
+mod artwork;
+mod beat_grid;
+mod decode;
+mod flac;
+mod key;
+mod mp4;
+mod mpeg;
+mod playlist;
+mod rekordbox_xml;
+mod riff;
+mod serato;
+mod waveform;
+
+pub use analysis::{analyse, Analysis};
+pub use key::Key;
+pub use playlist::{normalize_path, read_playlist, scan_playlists, PlaylistFile};
+pub use rekordbox_xml::read_collection_cues;
+
+/// Optional, slower passes performed while scanning a library.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanOptions {
+    /// Analyse the audio of tracks without a (recognised) key tag.
+    pub detect_keys: bool,
+}
+
+/// A folder holding music, optionally narrowed down by globs matched against
+/// paths relative to the folder.
+#[derive(Debug, Clone)]
+pub struct LibraryRoot {
+    pub path: PathBuf,
+    include: GlobSet,
+    exclude: GlobSet,
+}
+
+impl LibraryRoot {
+    /// Every audio file below `path`.
+    pub fn new<T: AsRef<Path>>(path: T) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            include: GlobSet::empty(),
+            exclude: GlobSet::empty(),
+        }
+    }
+
+    /// Only files matching one of `include` (all when empty) and none of `exclude`.
+    pub fn with_globs<T: AsRef<Path>>(path: T, include: &[String], exclude: &[String]) -> Result<Self, globset::Error> {
+        let build = |patterns: &[String]| -> Result<GlobSet, globset::Error> {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(Glob::new(pattern)?);
+            }
+            builder.build()
+        };
+
+        Ok(Self {
+            path: path.as_ref().to_path_buf(),
+            include: build(include)?,
+            exclude: build(exclude)?,
+        })
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        let relative = match path.strip_prefix(&self.path) {
+            Ok(relative) => relative,
+            Err(_) => return false,
+        };
+
+        (self.include.is_empty() || self.include.is_match(relative)) && !self.exclude.is_match(relative)
+    }
+}
+
+/// An audio file below the library root, as seen on disk before reading its tags.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AudioFile {
+    pub path: PathBuf,
+    pub size: u32,
+    pub modified: Option<SystemTime>,
+}
+
+/// Audio containers the scanner knows how to read metadata from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum AudioFormat {
+    Mp3,
+    Flac,
+    Wav,
+    Aiff,
+    M4a,
+}
+
+impl AudioFormat {
+    pub fn from_path<T: AsRef<Path>>(path: T) -> Option<AudioFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        Some(match extension.as_str() {
+            "mp3" => AudioFormat::Mp3,
+            "flac" => AudioFormat::Flac,
+            "wav" | "wave" => AudioFormat::Wav,
+            "aif" | "aiff" => AudioFormat::Aiff,
+            "m4a" | "mp4" => AudioFormat::M4a,
+            _ => return None,
+        })
+    }
+}
+
 fn is_hidden(entry: &DirEntry) -> bool {
     entry.file_name()
          .to_str()
@@ -21,42 +131,216 @@ fn is_regular_file(entry: &DirEntry) -> bool {
     entry.file_type().is_file()
 }
 
-fn has_mp3_extension(entry: &DirEntry) -> bool {
-    entry.path().extension() == Some(OsStr::new("mp3"))
+fn has_audio_extension(entry: &DirEntry) -> bool {
+    AudioFormat::from_path(entry.path()).is_some()
 }
 
-fn mp3_files_iterator<T: AsRef<Path>>(t: T) -> impl Iterator<Item = DirEntry> {
+fn audio_files_iterator<T: AsRef<Path>>(t: T) -> impl Iterator<Item = DirEntry> {
     WalkDir::new(t)
         .into_iter()
         .filter_entry(|e| !is_hidden(e))
         .filter_map(|e| e.ok())
         .filter(is_regular_file)
-        .filter(has_mp3_extension)
+        .filter(has_audio_extension)
 }
 
-fn extract_bpm(tag: &Tag) -> Option<u32> {
-    match tag.get("TBPM") {
-        Some(frame) => {
-            match frame.content() {
-                id3::Content::Text(text) => {
-                    match text.parse::<u32>() {
-                        Ok(value) => Some(value * 100),
-                        _ => None,
-                    }
-                },
-                _ => None,
-            }
+/// The DB protocol only carries whole seconds.
+fn whole_seconds(duration: Duration) -> u32 {
+    duration.as_secs_f64().round() as u32
+}
+
+/// Parse a free text tempo into the 1/100 BPM precision used by the DB protocol.
+///
+/// Taggers write anything from "128" to "127.50", "128,0" or "128 BPM".
+fn parse_bpm(text: &str) -> Option<u32> {
+    let number = text
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || *c == '.' || *c == ',')
+        .collect::<String>()
+        .replace(',', ".");
+
+    match number.parse::<f64>() {
+        Ok(value) if value > 0.0 && value < 1000.0 => Some((value * 100.0).round() as u32),
+        _ => None,
+    }
+}
+
+/// Dates come in many shapes ("2019", "2019-04-23", ...), only the year is kept.
+fn parse_year(text: &str) -> Option<u32> {
+    text.trim()
+        .get(0..4)
+        .and_then(|year| year.parse::<u32>().ok())
+        .filter(|year| *year > 0)
+}
+
+/// POPM ratings range from 0 to 255, mapped to stars the way most taggers do.
+fn stars_from_popularimeter(value: u8) -> u8 {
+    match value {
+        0 => 0,
+        1..=31 => 1,
+        32..=95 => 2,
+        96..=159 => 3,
+        160..=223 => 4,
+        _ => 5,
+    }
+}
+
+fn text_frame(tag: &Tag, id: &str) -> String {
+    tag.get(id)
+        .and_then(|frame| frame.content().text())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn user_text<'a>(tag: &'a Tag, description: &str) -> Option<&'a str> {
+    tag.extended_texts()
+        .find(|text| text.description.eq_ignore_ascii_case(description))
+        .map(|text| text.value.as_str())
+}
+
+fn extract_year(tag: &Tag) -> Option<u32> {
+    tag.year()
+        .or_else(|| tag.date_recorded().map(|timestamp| timestamp.year))
+        .filter(|year| *year > 0)
+        .map(|year| year as u32)
+}
+
+/// iTunes stores a bunch of technical COMM frames, prefer the one without description.
+fn extract_comment(tag: &Tag) -> String {
+    tag.comments()
+        .find(|comment| comment.description.is_empty())
+        .or_else(|| tag.comments().next())
+        .map(|comment| comment.text.clone())
+        .unwrap_or_default()
+}
+
+fn extract_rating(tag: &Tag) -> Option<u8> {
+    match tag.get("POPM")?.content() {
+        id3::Content::Unknown(data) => {
+            // NUL terminated e-mail address followed by the rating byte and play counter.
+            let separator = data.iter().position(|byte| *byte == 0)?;
+            data.get(separator + 1).map(|rating| stars_from_popularimeter(*rating))
         },
         _ => None,
     }
 }
 
+fn extract_artwork(tag: &Tag) -> Option<Artwork> {
+    let picture = tag.pictures()
+        .find(|picture| picture.picture_type == PictureType::CoverFront)
+        .or_else(|| tag.pictures().next())?;
+
+    Some(Artwork {
+        mime_type: picture.mime_type.clone(),
+        data: Bytes::from(picture.data.clone()),
+    })
+}
+
+/// Split a GEOB frame into its description and the encapsulated object.
+fn general_encapsulated_object(data: &[u8]) -> Option<(String, &[u8])> {
+    let (encoding, mut rest) = data.split_first()?;
+    let wide = *encoding == 1 || *encoding == 2;
+
+    // MIME type is always latin1
+    let mime_end = rest.iter().position(|byte| *byte == 0)?;
+    rest = &rest[mime_end + 1..];
+
+    let mut fields = vec![];
+    for _ in 0..2 {
+        let end = if wide {
+            (0..rest.len() / 2).map(|i| i * 2).find(|i| rest[*i] == 0 && rest[*i + 1] == 0)?
+        } else {
+            rest.iter().position(|byte| *byte == 0)?
+        };
+        fields.push(&rest[..end]);
+        rest = &rest[end + if wide { 2 } else { 1 }..];
+    }
+
+    Some((decode_text(*encoding, fields[1]), rest))
+}
+
+/// Decode a text field of an ID3v2 frame stored in `encoding`: latin1, UTF-16
+/// with a byte order mark, UTF-16BE or UTF-8.
+fn decode_text(encoding: u8, bytes: &[u8]) -> String {
+    match encoding {
+        0 => bytes.iter().map(|byte| *byte as char).collect(),
+        1 | 2 => {
+            let (big_endian, bytes) = match bytes {
+                [0xfe, 0xff, rest @ ..] => (true, rest),
+                [0xff, 0xfe, rest @ ..] => (false, rest),
+                _ => (encoding == 2, bytes),
+            };
+
+            let units = bytes
+                .chunks_exact(2)
+                .map(|unit| match big_endian {
+                    true => u16::from_be_bytes([unit[0], unit[1]]),
+                    false => u16::from_le_bytes([unit[0], unit[1]]),
+                })
+                .collect::<Vec<u16>>();
+            String::from_utf16_lossy(&units)
+        },
+        _ => String::from_utf8_lossy(bytes).to_string(),
+    }
+}
+
+fn find_general_encapsulated_object<'a>(tag: &'a Tag, description: &str) -> Option<&'a [u8]> {
+    tag.frames()
+        .filter(|frame| frame.id() == "GEOB")
+        .filter_map(|frame| match frame.content() {
+            id3::Content::Unknown(data) => general_encapsulated_object(data),
+            _ => None,
+        })
+        .find(|(found, _)| found == description)
+        .map(|(_, object)| object)
+}
+
+/// Serato stores its analyzed tempo in a GEOB frame called "Serato Autotags",
+/// a two byte version followed by NUL terminated ascii values (bpm, auto gain, gain).
+fn extract_serato_bpm(tag: &Tag) -> Option<u32> {
+    find_general_encapsulated_object(tag, "Serato Autotags")
+        .and_then(|object| {
+            let values = object.get(2..)?;
+            let end = values.iter().position(|byte| *byte == 0).unwrap_or(values.len());
+            parse_bpm(&String::from_utf8_lossy(&values[..end]))
+        })
+}
+
+/// The tempo from TBPM, or else from a user defined text frame or Serato's
+/// analysis. Traktor keeps its own analysis in an undocumented PRIV frame,
+/// which isn't read: it writes TBPM alongside, so its tempo is found anyway.
+fn extract_bpm(tag: &Tag) -> Option<u32> {
+    tag.get("TBPM")
+        .and_then(|frame| frame.content().text())
+        .and_then(parse_bpm)
+        .or_else(|| {
+            ["BPM", "FBPM"]
+                .iter()
+                .find_map(|description| user_text(tag, description))
+                .and_then(parse_bpm)
+        })
+        .or_else(|| extract_serato_bpm(tag))
+}
+
 fn extract_id3v2(tag: Tag) -> Metadata {
     Metadata {
         artist: tag.artist().unwrap_or("").to_string(),
         title: tag.title().unwrap_or("").to_string(),
         bpm: extract_bpm(&tag),
         album: tag.album().unwrap_or("").to_string(),
+        duration: None,
+        genre: tag.genre().unwrap_or("").to_string(),
+        key: text_frame(&tag, "TKEY"),
+        label: text_frame(&tag, "TPUB"),
+        year: extract_year(&tag),
+        comment: extract_comment(&tag),
+        rating: extract_rating(&tag),
+        color: user_text(&tag, "COLOR").and_then(Color::from_name),
+        artwork: extract_artwork(&tag),
+        cues: find_general_encapsulated_object(&tag, serato::MARKERS_DESCRIPTION)
+            .map(serato::markers)
+            .unwrap_or_default(),
     }
 }
 
@@ -66,6 +350,9 @@ fn extract_id3v1<'a>(tag: id3v1::Tag) -> Metadata {
         title: tag.title,
         bpm: None,
         album: tag.album,
+        year: parse_year(&tag.year),
+        comment: tag.comment,
+        ..Default::default()
     }
 }
 
@@ -91,37 +378,272 @@ impl From<std::io::Error> for LibraryError {
     }
 }
 
-fn metadata_extractor(entry: DirEntry) -> Option<(Metadata, PathBuf, u32)> {
-    let extracted_metadata = match Tag::read_from_path(entry.path()) {
-        Ok(tag) => extract_id3v2(tag),
+fn read_mp3_metadata(path: &Path) -> Option<Metadata> {
+    let mut metadata = read_mp3_tags(path)?;
+
+    if let Ok(mut file) = File::open(path) {
+        metadata.duration = mpeg::duration(&mut file).map(whole_seconds);
+    }
+
+    Some(metadata)
+}
+
+fn read_mp3_tags(path: &Path) -> Option<Metadata> {
+    match Tag::read_from_path(path) {
+        Ok(tag) => Some(extract_id3v2(tag)),
         Err(_) => {
-            match File::open(entry.path()) {
+            match File::open(path) {
                 Ok(file) => {
                     match id3v1::Tag::read_from(file) {
-                        Ok(tag) => extract_id3v1(tag),
-                        Err(_err) => return None,
+                        Ok(tag) => Some(extract_id3v1(tag)),
+                        Err(_err) => None,
                     }
                 },
-                Err(_) => return None,
+                Err(_) => None,
             }
         },
-    };
-
-    match metadata(entry.path()) {
-        Ok(attributes) => {
-            Some((
-                extracted_metadata,
-                entry.path().to_path_buf(),
-                attributes.size() as u32,
-            ))
-        },
-        _ => None,
     }
 }
 
-pub fn scan_folder<T: AsRef<Path>>(path: T) -> Vec<Track> {
-    mp3_files_iterator(path)
-        .filter_map(metadata_extractor)
-        .map(|(metadata, path, file_size)| Track::new(metadata, path, file_size))
+fn read_metadata(format: AudioFormat, path: &Path) -> Option<Metadata> {
+    if format == AudioFormat::Mp3 {
+        return read_mp3_metadata(path);
+    }
+
+    let mut file = File::open(path).ok()?;
+    match format {
+        AudioFormat::Flac => flac::read_metadata(&mut file),
+        AudioFormat::Wav | AudioFormat::Aiff => riff::read_metadata(&mut file),
+        AudioFormat::M4a => mp4::read_metadata(&mut file),
+        AudioFormat::Mp3 => None,
+    }
+}
+
+/// Untagged files would otherwise show up as empty rows on the players.
+fn fallback_title(mut metadata: Metadata, path: &Path) -> Metadata {
+    if metadata.title.is_empty() {
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            metadata.title = stem.to_string();
+        }
+    }
+
+    metadata
+}
+
+/// Rewrite the key tag in classic notation, unrecognised keys are dropped so
+/// they can be detected instead.
+fn normalize_key(mut metadata: Metadata) -> Metadata {
+    metadata.key = Key::parse(&metadata.key)
+        .map(|key| key.to_string())
+        .unwrap_or_default();
+
+    metadata
+}
+
+/// Tracks without embedded artwork take the cover image of their folder,
+/// either way it's scaled down to what the players show.
+fn scale_artwork(mut metadata: Metadata, path: &Path) -> Metadata {
+    metadata.artwork = metadata.artwork.take()
+        .or_else(|| artwork::folder_artwork(path))
+        .and_then(|artwork| artwork::thumbnail(&artwork));
+
+    metadata
+}
+
+fn detect_missing_key(mut metadata: Metadata, path: &Path, options: &ScanOptions) -> Metadata {
+    if options.detect_keys && metadata.key.is_empty() {
+        if let Some(key) = decode::decode(path, decode::ANALYSIS_SAMPLE_RATE).and_then(|signal| key::detect(&signal)) {
+            metadata.key = key.to_string();
+        }
+    }
+
+    metadata
+}
+
+/// Find the audio files of a library without reading them, cheap enough to
+/// compare a library against what was scanned before.
+pub fn audio_files(root: &LibraryRoot) -> Vec<AudioFile> {
+    audio_files_below(root, &root.path)
+}
+
+/// Audio files of `root` at or below `path`, which may be a single file.
+pub fn audio_files_below(root: &LibraryRoot, path: &Path) -> Vec<AudioFile> {
+    audio_files_iterator(path)
+        .filter(|entry| root.contains(entry.path()))
+        .filter_map(|entry| {
+            let attributes = metadata(entry.path()).ok()?;
+
+            Some(AudioFile {
+                path: entry.path().to_path_buf(),
+                size: attributes.size() as u32,
+                modified: attributes.modified().ok(),
+            })
+        })
         .collect()
 }
+
+/// Read the tags of a single audio file.
+pub fn read_track(file: &AudioFile, options: &ScanOptions) -> Option<Track> {
+    let format = AudioFormat::from_path(&file.path)?;
+    let metadata = normalize_key(fallback_title(read_metadata(format, &file.path)?, &file.path));
+    let metadata = scale_artwork(metadata, &file.path);
+
+    Some(Track::new(
+        detect_missing_key(metadata, &file.path, options),
+        file.path.clone(),
+        file.size,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_detects_audio_formats_by_extension() {
+        assert_eq!(Some(AudioFormat::Mp3), AudioFormat::from_path("a/b.mp3"));
+        assert_eq!(Some(AudioFormat::Flac), AudioFormat::from_path("a/b.FLAC"));
+        assert_eq!(Some(AudioFormat::Wav), AudioFormat::from_path("b.wav"));
+        assert_eq!(Some(AudioFormat::Aiff), AudioFormat::from_path("b.aif"));
+        assert_eq!(Some(AudioFormat::Aiff), AudioFormat::from_path("b.aiff"));
+        assert_eq!(Some(AudioFormat::M4a), AudioFormat::from_path("b.m4a"));
+        assert_eq!(None, AudioFormat::from_path("b.txt"));
+        assert_eq!(None, AudioFormat::from_path("mp3"));
+    }
+
+    #[test]
+    fn it_narrows_library_roots_down_by_globs() {
+        let include = vec!["**/*.flac".to_string(), "Sets/**".to_string()];
+        let exclude = vec!["**/Samples/**".to_string()];
+        let root = LibraryRoot::with_globs("/music", &include, &exclude).unwrap();
+
+        assert!(root.contains(Path::new("/music/House/a.flac")));
+        assert!(root.contains(Path::new("/music/Sets/b.mp3")));
+        assert!(!root.contains(Path::new("/music/House/b.mp3")));
+        assert!(!root.contains(Path::new("/music/House/Samples/a.flac")));
+        assert!(!root.contains(Path::new("/other/a.flac")));
+        assert!(LibraryRoot::new("/music").contains(Path::new("/music/House/b.mp3")));
+        assert!(LibraryRoot::with_globs("/music", &["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn it_normalizes_keys_to_classic_notation() {
+        let metadata = |key: &str| Metadata { key: key.to_string(), ..Default::default() };
+
+        assert_eq!("Am", normalize_key(metadata("8A")).key);
+        assert_eq!("F#", normalize_key(metadata("F# major")).key);
+        assert_eq!("", normalize_key(metadata("unknown")).key);
+    }
+
+    #[test]
+    fn it_parses_fractional_bpm() {
+        assert_eq!(Some(12800), parse_bpm("128"));
+        assert_eq!(Some(12750), parse_bpm("127.50"));
+        assert_eq!(Some(12800), parse_bpm("128.0"));
+        assert_eq!(Some(12425), parse_bpm("124,25"));
+        assert_eq!(Some(17400), parse_bpm(" 174 BPM"));
+        assert_eq!(Some(9999), parse_bpm("99.987"));
+        assert_eq!(None, parse_bpm("0"));
+        assert_eq!(None, parse_bpm(""));
+        assert_eq!(None, parse_bpm("fast"));
+    }
+
+    #[test]
+    fn it_falls_back_to_user_defined_bpm_frames() {
+        let mut tag = Tag::new();
+        tag.add_extended_text("BPM", "126.5");
+
+        assert_eq!(Some(12650), extract_bpm(&tag));
+
+        tag.set_text("TBPM", "128");
+        assert_eq!(Some(12800), extract_bpm(&tag));
+    }
+
+    #[test]
+    fn it_falls_back_to_serato_autotags() {
+        let mut data = b"\x00application/octet-stream\x00\x00Serato Autotags\x00".to_vec();
+        data.extend(b"\x01\x01115.50\x00-3.257\x000.000\x00");
+
+        let mut tag = Tag::new();
+        tag.add_frame(id3::Frame::with_content("GEOB", id3::Content::Unknown(data)));
+
+        assert_eq!(Some(11550), extract_bpm(&tag));
+    }
+
+    #[test]
+    fn it_reads_utf16_geob_descriptions_in_either_byte_order() {
+        let utf16 = |text: &str, big_endian: bool| text.encode_utf16()
+            .chain(Some(0))
+            .flat_map(|unit| match big_endian {
+                true => unit.to_be_bytes(),
+                false => unit.to_le_bytes(),
+            })
+            .collect::<Vec<u8>>();
+
+        for (encoding, bom, big_endian) in [(1u8, &b"\xfe\xff"[..], true), (1, b"\xff\xfe", false), (2, b"", true)] {
+            let mut data = vec![encoding];
+            data.extend(b"application/octet-stream\x00");
+            data.extend(bom);
+            data.extend(utf16("", big_endian));
+            data.extend(bom);
+            data.extend(utf16("Serato Autotags", big_endian));
+            data.extend(b"\x01\x01120.00\x00");
+
+            let mut tag = Tag::new();
+            tag.add_frame(id3::Frame::with_content("GEOB", id3::Content::Unknown(data)));
+
+            assert_eq!(Some(12000), extract_bpm(&tag));
+        }
+    }
+
+    #[test]
+    fn it_reads_latin1_geob_descriptions() {
+        assert_eq!("Caf\u{e9}", decode_text(0, b"Caf\xe9"));
+    }
+
+    #[test]
+    fn it_extracts_the_full_id3v2_tag_set() {
+        let mut tag = Tag::new();
+        tag.set_artist("Loopmasters");
+        tag.set_title("Demo Track 1");
+        tag.set_genre("House");
+        tag.set_text("TKEY", "Am");
+        tag.set_text("TPUB", "Loopmasters Records");
+        tag.set_text("TYER", "2019");
+        tag.add_extended_text("COLOR", "Aqua");
+        tag.add_comment(id3::frame::Comment {
+            lang: "eng".to_string(),
+            description: "iTunNORM".to_string(),
+            text: "00000000".to_string(),
+        });
+        tag.add_comment(id3::frame::Comment {
+            lang: "eng".to_string(),
+            description: "".to_string(),
+            text: "Tracks by www.loopmasters.com".to_string(),
+        });
+        tag.add_frame(id3::Frame::with_content(
+            "POPM",
+            id3::Content::Unknown(b"someone@example.com\0\xc4\0\0\0\x01".to_vec()),
+        ));
+        tag.add_picture(id3::frame::Picture {
+            mime_type: "image/jpeg".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: "".to_string(),
+            data: vec![0xff, 0xd8, 0xff],
+        });
+
+        let metadata = extract_id3v2(tag);
+
+        assert_eq!("House", metadata.genre);
+        assert_eq!("Am", metadata.key);
+        assert_eq!("Loopmasters Records", metadata.label);
+        assert_eq!(Some(2019), metadata.year);
+        assert_eq!("Tracks by www.loopmasters.com", metadata.comment);
+        assert_eq!(Some(4), metadata.rating);
+        assert_eq!(Some(Color::Aqua), metadata.color);
+        assert_eq!(Some(Artwork {
+            mime_type: "image/jpeg".to_string(),
+            data: Bytes::from(vec![0xff, 0xd8, 0xff]),
+        }), metadata.artwork);
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::beat_grid::BeatGrid;
+use super::decode::{decode, ANALYSIS_SAMPLE_RATE};
+use super::waveform::{DetailWaveform, PreviewWaveform};
+
+/// Everything derived from the decoded audio of a track, computed in a single
+/// decoding pass since decoding dominates the time spent.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Analysis {
+    pub preview: PreviewWaveform,
+    pub detail: DetailWaveform,
+    pub beat_grid: BeatGrid,
+}
+
+pub fn analyse<T: AsRef<Path>>(path: T) -> Option<Analysis> {
+    let signal = decode(path, ANALYSIS_SAMPLE_RATE)?;
+
+    Some(Analysis {
+        preview: PreviewWaveform::analyse(&signal),
+        detail: DetailWaveform::analyse(&signal),
+        beat_grid: BeatGrid::analyse(&signal),
+    })
+}
@@ -0,0 +1,79 @@
+use bytes::Bytes;
+use image::{imageops::FilterType, DynamicImage, ImageOutputFormat};
+use std::io::Cursor;
+use std::path::Path;
+
+use crate::rekordbox::Artwork;
+
+/// Width and height of the album art served to players, the size rekordbox
+/// exports its thumbnails in.
+pub const ARTWORK_SIZE: u32 = 80;
+
+const JPEG_QUALITY: u8 = 90;
+
+/// Images next to a track used when it has no embedded artwork, in order of preference.
+const FOLDER_IMAGES: [&str; 4] = ["cover.jpg", "folder.jpg", "Cover.jpg", "Folder.jpg"];
+
+/// The cover image kept in the folder of the track at `path`, if there is one.
+pub fn folder_artwork(path: &Path) -> Option<Artwork> {
+    let folder = path.parent()?;
+
+    FOLDER_IMAGES.iter().find_map(|name| {
+        let data = std::fs::read(folder.join(name)).ok()?;
+
+        Some(Artwork {
+            mime_type: "image/jpeg".to_string(),
+            data: Bytes::from(data),
+        })
+    })
+}
+
+/// Crop the artwork to a square and scale it to the size players show, as a
+/// JPEG since that's the only format they decode. Images that can't be
+/// decoded are dropped.
+pub fn thumbnail(artwork: &Artwork) -> Option<Artwork> {
+    let image = image::load_from_memory(&artwork.data).ok()?;
+    let thumbnail = image.resize_to_fill(ARTWORK_SIZE, ARTWORK_SIZE, FilterType::Triangle);
+
+    let mut data = Cursor::new(vec![]);
+    DynamicImage::ImageRgb8(thumbnail.to_rgb8())
+        .write_to(&mut data, ImageOutputFormat::Jpeg(JPEG_QUALITY))
+        .ok()?;
+
+    Some(Artwork {
+        mime_type: "image/jpeg".to_string(),
+        data: Bytes::from(data.into_inner()),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use image::{GenericImageView, RgbImage};
+
+    #[test]
+    fn it_scales_artwork_down_to_a_square_jpeg() {
+        let mut png = Cursor::new(vec![]);
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(600, 400, image::Rgb([200, 20, 20])))
+            .write_to(&mut png, ImageOutputFormat::Png)
+            .unwrap();
+
+        let artwork = thumbnail(&Artwork {
+            mime_type: "image/png".to_string(),
+            data: Bytes::from(png.into_inner()),
+        }).unwrap();
+        let image = image::load_from_memory(&artwork.data).unwrap();
+
+        assert_eq!("image/jpeg", artwork.mime_type);
+        assert_eq!(&artwork.data[0..2], &[0xff, 0xd8]);
+        assert_eq!((ARTWORK_SIZE, ARTWORK_SIZE), image.dimensions());
+    }
+
+    #[test]
+    fn it_drops_images_it_cannot_decode() {
+        assert_eq!(None, thumbnail(&Artwork {
+            mime_type: "image/jpeg".to_string(),
+            data: Bytes::from_static(b"not an image"),
+        }));
+    }
+}
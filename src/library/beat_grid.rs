@@ -0,0 +1,244 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+use super::decode::Signal;
+
+/// Samples per frame of the onset envelope, about 6ms at the analysis sample rate.
+const HOP: usize = 64;
+
+/// Range of tempos considered, an octave plus some slack for the slower genres.
+const MIN_BPM: f64 = 78.0;
+const MAX_BPM: f64 = 180.0;
+
+/// Most dance music is around this tempo, it decides between tempos that are
+/// about equally likely, like a half time feel of a fast track.
+const PREFERRED_BPM: f64 = 125.0;
+
+/// Kick drums are below this frequency, their onsets are weighted in separately.
+const BASS_CUTOFF: f64 = 150.0;
+
+const BEATS_PER_BAR: usize = 4;
+
+/// Frames around a beat its onset is looked for in.
+const ONSET_SLACK: usize = 2;
+
+/// Bytes preceding the beats in the beat grid response, players skip them.
+const GRID_LEADING_BYTES: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Beat {
+    /// Position in the bar, 1 to 4.
+    pub beat_in_bar: u8,
+    /// Milliseconds since the start of the track.
+    pub time: u32,
+}
+
+/// The position of every beat in a track, for quantize, beat jumps and sync.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct BeatGrid {
+    /// Tempo in hundredths of a BPM.
+    pub tempo: u16,
+    pub beats: Vec<Beat>,
+}
+
+impl BeatGrid {
+    /// Detect a grid with a constant tempo, which is what the music played on
+    /// CDJs is produced with. Returns an empty grid when no beat is found.
+    pub fn analyse(signal: &Signal) -> Self {
+        let onsets = onset_envelope(signal);
+        let frame_rate = signal.sample_rate as f64 / HOP as f64;
+
+        if onsets.iter().all(|onset| *onset == 0.0) {
+            return Self::default();
+        }
+
+        let weighted = |bpm: f64| {
+            let octaves = (bpm / PREFERRED_BPM).log2();
+            periodicity(&onsets, frame_rate, bpm).0 * (-2.0 * octaves * octaves).exp()
+        };
+        let coarse = best_of(steps(MIN_BPM, MAX_BPM, 0.5), weighted);
+        let bpm = best_of(steps(coarse - 0.5, coarse + 0.5, 0.01), |bpm| periodicity(&onsets, frame_rate, bpm).0);
+
+        let period = frame_rate * 60.0 / bpm;
+        let (_, phase) = periodicity(&onsets, frame_rate, bpm);
+        let first = (-phase / (2.0 * PI) * period).rem_euclid(period);
+        let frames = (0..)
+            .map(|beat| first + beat as f64 * period)
+            .take_while(|frame| (*frame as usize) < onsets.len())
+            .collect::<Vec<f64>>();
+
+        // Downbeats tend to be accented, by a crash or a bass change.
+        let accent = |frame: f64| {
+            let start = (frame as usize).saturating_sub(ONSET_SLACK);
+            let end = std::cmp::min(frame as usize + ONSET_SLACK + 1, onsets.len());
+            onsets[start..end].iter().fold(f64::MIN, |max, onset| max.max(*onset))
+        };
+        let downbeat = best_of((0..BEATS_PER_BAR).collect(), |offset| {
+            frames.iter().skip(offset).step_by(BEATS_PER_BAR).map(|frame| accent(*frame)).sum::<f64>()
+        });
+
+        let beats = frames.iter()
+            .enumerate()
+            .map(|(index, frame)| Beat {
+                beat_in_bar: ((index + BEATS_PER_BAR - downbeat) % BEATS_PER_BAR) as u8 + 1,
+                time: (frame * HOP as f64 * 1000.0 / signal.sample_rate as f64).round() as u32,
+            })
+            .collect();
+
+        Self {
+            tempo: (bpm * 100.0).round() as u16,
+            beats,
+        }
+    }
+
+    /// The payload of the beat grid response, 16 bytes per beat holding the
+    /// beat in the bar, tempo and time, little endian unlike the rest of the protocol.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(GRID_LEADING_BYTES + 16 * self.beats.len());
+
+        bytes.put_slice(&[0; GRID_LEADING_BYTES]);
+        for beat in &self.beats {
+            bytes.put_u16_le(beat.beat_in_bar as u16);
+            bytes.put_u16_le(self.tempo);
+            bytes.put_u32_le(beat.time);
+            bytes.put_u64(0);
+        }
+
+        bytes.freeze()
+    }
+}
+
+/// How strongly onsets rise, per frame. Rises in the overall and the bass
+/// energy are added up so both kick drums and other percussion count.
+fn onset_envelope(signal: &Signal) -> Vec<f64> {
+    let alpha = 1.0 - (-2.0 * PI * BASS_CUTOFF / signal.sample_rate as f64).exp();
+    let mut bass = 0.0;
+    let mut previous: Option<(f64, f64)> = None;
+
+    let onsets = signal.samples.chunks(HOP)
+        .map(|frame| {
+            let (mut full_energy, mut bass_energy) = (0.0, 0.0);
+            for sample in frame {
+                let sample = *sample as f64;
+                bass += alpha * (sample - bass);
+                full_energy += sample * sample;
+                bass_energy += bass * bass;
+            }
+
+            let level = |energy: f64| (energy / frame.len() as f64 + 1e-6).ln();
+            let current = (level(full_energy), level(bass_energy));
+            let onset = match previous {
+                Some((full, bass)) => (current.0 - full).max(0.0) + (current.1 - bass).max(0.0),
+                None => 0.0,
+            };
+            previous = Some(current);
+
+            onset
+        })
+        .collect::<Vec<f64>>();
+
+    let mean = onsets.iter().sum::<f64>() / onsets.len().max(1) as f64;
+    onsets.into_iter().map(|onset| onset - mean).collect()
+}
+
+/// Strength and phase (radians) of the onsets recurring at `bpm`, from their
+/// Fourier coefficient at the frequency of the beats.
+fn periodicity(onsets: &[f64], frame_rate: f64, bpm: f64) -> (f64, f64) {
+    let step = 2.0 * PI * bpm / 60.0 / frame_rate;
+    let (step_cos, step_sin) = (step.cos(), step.sin());
+    let (mut cos, mut sin) = (1.0, 0.0);
+    let (mut real, mut imaginary) = (0.0, 0.0);
+
+    for onset in onsets {
+        real += onset * cos;
+        imaginary -= onset * sin;
+        let next_cos = cos * step_cos - sin * step_sin;
+        sin = sin * step_cos + cos * step_sin;
+        cos = next_cos;
+    }
+
+    ((real * real + imaginary * imaginary).sqrt(), imaginary.atan2(real))
+}
+
+fn steps(from: f64, to: f64, step: f64) -> Vec<f64> {
+    let count = ((to - from) / step).round() as usize;
+
+    (0..=count).map(|index| from + index as f64 * step).collect()
+}
+
+fn best_of<T: Copy, F: Fn(T) -> f64>(candidates: Vec<T>, score: F) -> T {
+    let mut best = candidates[0];
+    let mut best_score = f64::MIN;
+
+    for candidate in candidates {
+        let candidate_score = score(candidate);
+        if candidate_score > best_score {
+            best = candidate;
+            best_score = candidate_score;
+        }
+    }
+
+    best
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Decaying kick drums at `bpm`, the first of every bar louder.
+    fn kicks(bpm: f64, offset: f64, seconds: f64) -> Signal {
+        let sample_rate = 11025;
+        let period = 60.0 / bpm;
+
+        let samples = (0..(seconds * sample_rate as f64) as usize)
+            .map(|n| {
+                let time = n as f64 / sample_rate as f64 - offset;
+                if time < 0.0 {
+                    return 0.0;
+                }
+
+                let beat = (time / period) as usize;
+                let since = time - beat as f64 * period;
+                let accent = if beat.is_multiple_of(4) { 1.0 } else { 0.4 };
+
+                (accent * (2.0 * PI * 60.0 * since).sin() * (-since * 30.0).exp()) as f32
+            })
+            .collect();
+
+        Signal { samples, sample_rate }
+    }
+
+    #[test]
+    fn it_finds_the_tempo_phase_and_downbeats_of_a_kick_drum() {
+        let grid = BeatGrid::analyse(&kicks(128.0, 0.25, 60.0));
+
+        assert!((grid.tempo as i32 - 12800).abs() <= 2, "tempo {}", grid.tempo);
+        assert!((grid.beats[0].time as i32 - 250).abs() <= 10, "first beat at {}", grid.beats[0].time);
+        assert_eq!(grid.beats[0].beat_in_bar, 1);
+        assert_eq!(grid.beats[5].beat_in_bar, 2);
+
+        let last = grid.beats.last().unwrap();
+        let expected = 250.0 + (grid.beats.len() - 1) as f64 * 60000.0 / 128.0;
+        assert!((last.time as f64 - expected).abs() <= 10.0, "last beat at {}", last.time);
+    }
+
+    #[test]
+    fn it_encodes_beats_little_endian() {
+        let grid = BeatGrid {
+            tempo: 12800,
+            beats: vec![Beat { beat_in_bar: 1, time: 250 }, Beat { beat_in_bar: 2, time: 719 }],
+        };
+        let bytes = grid.to_bytes();
+
+        assert_eq!(bytes.len(), 20 + 2 * 16);
+        assert_eq!(&bytes[36..44], &[2, 0, 0x00, 0x32, 0xcf, 0x02, 0, 0]);
+    }
+
+    #[test]
+    fn it_finds_no_beats_in_silence() {
+        let grid = BeatGrid::analyse(&Signal { samples: vec![0.0; 11025], sample_rate: 11025 });
+
+        assert_eq!(grid, BeatGrid::default());
+    }
+}
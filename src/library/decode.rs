@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// Sample rate the analysis passes work on, high enough to cover the pitches used
+/// for key detection while keeping decoded tracks small.
+pub const ANALYSIS_SAMPLE_RATE: u32 = 11025;
+
+/// Decoded mono audio.
+#[derive(Debug)]
+pub struct Signal {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+/// Decode an audio file to a mono signal, downsampled by averaging to roughly `target_rate`.
+pub fn decode<T: AsRef<Path>>(path: T, target_rate: u32) -> Option<Signal> {
+    let path = path.as_ref();
+    let source = MediaSourceStream::new(Box::new(File::open(path).ok()?), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let mut format = symphonia::default::get_probe()
+        .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?
+        .format;
+
+    let track = format.tracks().iter().find(|track| track.codec_params.codec != CODEC_TYPE_NULL)?;
+    let track_id = track.id;
+    let sample_rate = track.codec_params.sample_rate?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .ok()?;
+
+    let factor = std::cmp::max(1, sample_rate / target_rate) as usize;
+    let mut samples = vec![];
+    let (mut sum, mut count) = (0.0, 0);
+
+    while let Ok(packet) = format.next_packet() {
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Corrupt frames are skipped, the rest of the stream is still usable.
+            Err(Error::DecodeError(_)) => continue,
+            Err(_) => break,
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count();
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+
+        for frame in buffer.samples().chunks_exact(channels) {
+            sum += frame.iter().sum::<f32>() / channels as f32;
+            count += 1;
+            if count == factor {
+                samples.push(sum / factor as f32);
+                sum = 0.0;
+                count = 0;
+            }
+        }
+    }
+
+    Some(Signal {
+        samples,
+        sample_rate: sample_rate / factor as u32,
+    })
+}
@@ -0,0 +1,150 @@
+use bytes::Bytes;
+use metaflac::Tag;
+use metaflac::block::PictureType;
+use std::io::Read;
+use std::time::Duration;
+
+use crate::rekordbox::{Artwork, Color, Metadata};
+use super::{parse_bpm, parse_year, serato, whole_seconds};
+
+fn duration(tag: &Tag) -> Option<Duration> {
+    let stream_info = tag.get_streaminfo()?;
+    if stream_info.sample_rate == 0 || stream_info.total_samples == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(
+        stream_info.total_samples as f64 / stream_info.sample_rate as f64,
+    ))
+}
+
+fn first_comment(tag: &Tag, key: &str) -> Option<String> {
+    tag.get_vorbis(key)?.next().map(|value| value.to_string())
+}
+
+fn any_comment(tag: &Tag, keys: &[&str]) -> Option<String> {
+    keys.iter().find_map(|key| first_comment(tag, key))
+}
+
+/// There is no standard for ratings in Vorbis comments, both 0-5 and 0-100 scales are common.
+fn parse_rating(text: &str) -> Option<u8> {
+    let value = text.trim().parse::<u8>().ok()?;
+
+    Some(match value {
+        0..=5 => value,
+        _ => std::cmp::min(value, 100) / 20,
+    })
+}
+
+fn artwork(tag: &Tag) -> Option<Artwork> {
+    let picture = tag.pictures()
+        .find(|picture| picture.picture_type == PictureType::CoverFront)
+        .or_else(|| tag.pictures().next())?;
+
+    Some(Artwork {
+        mime_type: picture.mime_type.clone(),
+        data: Bytes::from(picture.data.clone()),
+    })
+}
+
+/// FLAC stores its tags as Vorbis comments in the metadata blocks.
+pub fn read_metadata<R: Read>(reader: &mut R) -> Option<Metadata> {
+    let tag = Tag::read_from(reader).ok()?;
+
+    Some(Metadata {
+        artist: first_comment(&tag, "ARTIST").unwrap_or_default(),
+        title: first_comment(&tag, "TITLE").unwrap_or_default(),
+        bpm: any_comment(&tag, &["BPM", "TEMPO"]).and_then(|bpm| parse_bpm(&bpm)),
+        album: first_comment(&tag, "ALBUM").unwrap_or_default(),
+        duration: duration(&tag).map(whole_seconds),
+        genre: first_comment(&tag, "GENRE").unwrap_or_default(),
+        key: any_comment(&tag, &["INITIALKEY", "KEY"]).unwrap_or_default(),
+        label: any_comment(&tag, &["LABEL", "ORGANIZATION", "PUBLISHER"]).unwrap_or_default(),
+        year: any_comment(&tag, &["DATE", "YEAR"]).and_then(|date| parse_year(&date)),
+        comment: any_comment(&tag, &["COMMENT", "DESCRIPTION"]).unwrap_or_default(),
+        rating: first_comment(&tag, "RATING").and_then(|rating| parse_rating(&rating)),
+        color: first_comment(&tag, "COLOR").and_then(|color| Color::from_name(&color)),
+        artwork: artwork(&tag),
+        cues: first_comment(&tag, "SERATO_MARKERS_V2")
+            .map(|markers| serato::markers_from_comment(&markers))
+            .unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn it_reads_vorbis_comments() {
+        let mut tag = Tag::new();
+        tag.set_vorbis("ARTIST", vec!["Loopmasters"]);
+        tag.set_vorbis("TITLE", vec!["Demo Track 1"]);
+        tag.set_vorbis("BPM", vec!["128"]);
+
+        let mut file = vec![];
+        tag.write_to(&mut file).unwrap();
+
+        assert_eq!(Some(Metadata {
+            artist: "Loopmasters".to_string(),
+            title: "Demo Track 1".to_string(),
+            bpm: Some(12800),
+            album: "".to_string(),
+            ..Default::default()
+        }), read_metadata(&mut Cursor::new(file)));
+    }
+
+    #[test]
+    fn it_keeps_fractional_bpm() {
+        let mut tag = Tag::new();
+        tag.set_vorbis("TEMPO", vec!["127.50"]);
+
+        let mut file = vec![];
+        tag.write_to(&mut file).unwrap();
+
+        assert_eq!(Some(12750), read_metadata(&mut Cursor::new(file)).unwrap().bpm);
+    }
+
+    #[test]
+    fn it_reads_the_full_tag_set() {
+        let mut tag = Tag::new();
+        tag.set_vorbis("GENRE", vec!["Techno"]);
+        tag.set_vorbis("INITIALKEY", vec!["8A"]);
+        tag.set_vorbis("ORGANIZATION", vec!["Loopmasters Records"]);
+        tag.set_vorbis("DATE", vec!["2019-04-23"]);
+        tag.set_vorbis("DESCRIPTION", vec!["Tracks by www.loopmasters.com"]);
+        tag.set_vorbis("RATING", vec!["80"]);
+        tag.add_picture("image/png", PictureType::CoverFront, vec![0x89, 0x50]);
+
+        let mut file = vec![];
+        tag.write_to(&mut file).unwrap();
+        let metadata = read_metadata(&mut Cursor::new(file)).unwrap();
+
+        assert_eq!("Techno", metadata.genre);
+        assert_eq!("8A", metadata.key);
+        assert_eq!("Loopmasters Records", metadata.label);
+        assert_eq!(Some(2019), metadata.year);
+        assert_eq!("Tracks by www.loopmasters.com", metadata.comment);
+        assert_eq!(Some(4), metadata.rating);
+        assert_eq!("image/png", metadata.artwork.unwrap().mime_type);
+    }
+
+    #[test]
+    fn it_calculates_duration_from_stream_info() {
+        let mut stream_info = metaflac::block::StreamInfo::new();
+        stream_info.sample_rate = 44100;
+        stream_info.num_channels = 2;
+        stream_info.bits_per_sample = 16;
+        stream_info.total_samples = 44100 * 185;
+        stream_info.md5 = vec![0u8; 16];
+
+        let mut tag = Tag::new();
+        tag.set_streaminfo(stream_info);
+
+        let mut file = vec![];
+        tag.write_to(&mut file).unwrap();
+
+        assert_eq!(Some(185), read_metadata(&mut Cursor::new(file)).unwrap().duration);
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::decode::Signal;
+
+const MAJOR_NAMES: [&str; 12] = ["C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B"];
+const MINOR_NAMES: [&str; 12] = ["Cm", "C#m", "Dm", "Ebm", "Em", "Fm", "F#m", "Gm", "G#m", "Am", "Bbm", "Bm"];
+
+/// Krumhansl-Schmuckler key profiles, starting at the tonic.
+const MAJOR_PROFILE: [f64; 12] = [6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88];
+const MINOR_PROFILE: [f64; 12] = [6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17];
+
+/// Length of the analysis window in samples of the decoded signal.
+const FRAME_SIZE: usize = 4096;
+
+/// Octaves above C1 that are folded into the chromagram, C3 up to B6.
+const OCTAVES: std::ops::Range<i32> = 2..6;
+
+/// A musical key, stored as pitch class of the tonic (0 = C) and mode.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Key {
+    tonic: u8,
+    minor: bool,
+}
+
+impl Key {
+    /// Parse the notations written by taggers: classic ("Am", "F# minor", "Bbmaj"),
+    /// Camelot ("8A") and Open Key ("1m").
+    pub fn parse(text: &str) -> Option<Key> {
+        let text = text.trim();
+
+        parse_wheel(text).or_else(|| parse_classic(text))
+    }
+
+    /// Position on the Camelot wheel, 1 to 12.
+    pub fn camelot_number(&self) -> u8 {
+        // Relative majors share their number with the minor key.
+        let major = if self.minor { (self.tonic + 3) % 12 } else { self.tonic };
+
+        match (8 + 7 * major as u32) % 12 {
+            0 => 12,
+            number => number as u8,
+        }
+    }
+
+    pub fn is_minor(&self) -> bool {
+        self.minor
+    }
+
+    /// Harmonic distance on the Camelot wheel, switching between relative
+    /// major and minor counts as one step.
+    pub fn distance(&self, other: &Key) -> u8 {
+        let steps = (self.camelot_number() as i8 - other.camelot_number() as i8).rem_euclid(12) as u8;
+
+        std::cmp::min(steps, 12 - steps) + (self.minor != other.minor) as u8
+    }
+
+    fn from_camelot(number: u8, minor: bool) -> Key {
+        let major = (7 * (number as u32 + 12 - 8)) % 12;
+
+        Key {
+            tonic: if minor { ((major + 9) % 12) as u8 } else { major as u8 },
+            minor,
+        }
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = if self.minor { &MINOR_NAMES } else { &MAJOR_NAMES };
+
+        write!(f, "{}", names[self.tonic as usize])
+    }
+}
+
+/// Camelot ("8A"/"8B") and Open Key ("1m"/"1d") share the same wheel, offset by seven.
+fn parse_wheel(text: &str) -> Option<Key> {
+    let split = text.find(|c: char| !c.is_ascii_digit())?;
+    let number = text[..split].parse::<u8>().ok().filter(|n| (1..=12).contains(n))?;
+
+    match text[split..].to_lowercase().as_str() {
+        "a" => Some(Key::from_camelot(number, true)),
+        "b" => Some(Key::from_camelot(number, false)),
+        "m" => Some(Key::from_camelot((number + 6) % 12 + 1, true)),
+        "d" => Some(Key::from_camelot((number + 6) % 12 + 1, false)),
+        _ => None,
+    }
+}
+
+fn parse_classic(text: &str) -> Option<Key> {
+    let mut chars = text.chars();
+    let natural = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+
+    let mut rest = chars.as_str();
+    let mut tonic = natural;
+    if let Some(accidental) = rest.chars().next() {
+        match accidental {
+            '#' | '♯' => tonic += 1,
+            'b' | '♭' => tonic += 11,
+            _ => {},
+        }
+        if tonic != natural {
+            rest = &rest[accidental.len_utf8()..];
+        }
+    }
+
+    let minor = match rest.trim() {
+        "" | "M" | "maj" | "major" | "Maj" | "Major" => false,
+        "m" | "min" | "minor" | "Min" | "Minor" => true,
+        _ => return None,
+    };
+
+    Some(Key {
+        tonic: (tonic % 12) as u8,
+        minor,
+    })
+}
+
+/// Magnitude of a single frequency within a frame, a Goertzel filter is far
+/// cheaper than a full FFT when only 48 bins are of interest.
+fn goertzel(frame: &[f32], frequency: f64, sample_rate: u32) -> f64 {
+    let coefficient = 2.0 * (2.0 * std::f64::consts::PI * frequency / sample_rate as f64).cos();
+    let (mut previous, mut before_previous) = (0.0, 0.0);
+
+    for sample in frame {
+        let current = *sample as f64 + coefficient * previous - before_previous;
+        before_previous = previous;
+        previous = current;
+    }
+
+    (previous * previous + before_previous * before_previous - coefficient * previous * before_previous).sqrt()
+}
+
+fn chromagram(signal: &Signal) -> [f64; 12] {
+    let mut chroma = [0.0; 12];
+    let nyquist = signal.sample_rate as f64 / 2.0;
+
+    for frame in signal.samples.chunks_exact(FRAME_SIZE) {
+        for octave in OCTAVES {
+            for pitch_class in 0..12 {
+                // MIDI note 24 is C1
+                let note = 24 + 12 * octave + pitch_class;
+                let frequency = 440.0 * 2f64.powf((note - 69) as f64 / 12.0);
+                if frequency < nyquist {
+                    chroma[pitch_class as usize] += goertzel(frame, frequency, signal.sample_rate);
+                }
+            }
+        }
+    }
+
+    chroma
+}
+
+fn correlation(a: &[f64; 12], b: &[f64; 12]) -> f64 {
+    let mean_a = a.iter().sum::<f64>() / 12.0;
+    let mean_b = b.iter().sum::<f64>() / 12.0;
+
+    let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+    for i in 0..12 {
+        covariance += (a[i] - mean_a) * (b[i] - mean_b);
+        variance_a += (a[i] - mean_a).powi(2);
+        variance_b += (b[i] - mean_b).powi(2);
+    }
+
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+/// Estimate the key of a decoded signal by correlating its chromagram with
+/// the key profiles of all 24 keys.
+pub fn detect(signal: &Signal) -> Option<Key> {
+    let chroma = chromagram(signal);
+    if chroma.iter().all(|value| *value == 0.0) {
+        return None;
+    }
+
+    let mut best: Option<(f64, Key)> = None;
+    for tonic in 0..12 {
+        for (minor, profile) in [(false, &MAJOR_PROFILE), (true, &MINOR_PROFILE)].iter() {
+            let mut rotated = [0.0; 12];
+            for (interval, weight) in profile.iter().enumerate() {
+                rotated[(tonic + interval) % 12] = *weight;
+            }
+
+            let score = correlation(&chroma, &rotated);
+            if best.map(|(best_score, _)| score > best_score).unwrap_or(true) {
+                best = Some((score, Key { tonic: tonic as u8, minor: *minor }));
+            }
+        }
+    }
+
+    best.map(|(_, key)| key)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_classic_notation() {
+        assert_eq!(Some("Am".to_string()), Key::parse("Am").map(|key| key.to_string()));
+        assert_eq!(Some("F#m".to_string()), Key::parse("F# minor").map(|key| key.to_string()));
+        assert_eq!(Some("Bb".to_string()), Key::parse("Bbmaj").map(|key| key.to_string()));
+        assert_eq!(Some("Ebm".to_string()), Key::parse("D#m").map(|key| key.to_string()));
+        assert_eq!(Some("C".to_string()), Key::parse("c").map(|key| key.to_string()));
+        assert_eq!(None, Key::parse("H"));
+        assert_eq!(None, Key::parse("Am7"));
+    }
+
+    #[test]
+    fn it_parses_camelot_and_open_key_notation() {
+        assert_eq!(Key::parse("Am"), Key::parse("8A"));
+        assert_eq!(Key::parse("C"), Key::parse("8B"));
+        assert_eq!(Key::parse("E"), Key::parse("12B"));
+        assert_eq!(Key::parse("Am"), Key::parse("1m"));
+        assert_eq!(Key::parse("C"), Key::parse("1d"));
+        assert_eq!(Key::parse("F#m"), Key::parse("11a"));
+        assert_eq!(None, Key::parse("13A"));
+    }
+
+    #[test]
+    fn it_measures_distance_on_the_camelot_wheel() {
+        let a_minor = Key::parse("8A").unwrap();
+
+        assert_eq!(8, a_minor.camelot_number());
+        assert_eq!(0, a_minor.distance(&a_minor));
+        assert_eq!(1, a_minor.distance(&Key::parse("9A").unwrap()));
+        assert_eq!(1, a_minor.distance(&Key::parse("8B").unwrap()));
+        assert_eq!(2, a_minor.distance(&Key::parse("7B").unwrap()));
+        assert_eq!(5, a_minor.distance(&Key::parse("1A").unwrap()));
+    }
+
+    #[test]
+    fn it_detects_the_key_of_a_triad() {
+        let sample_rate = 11025;
+        // A minor triad, A3 C4 E4
+        let frequencies = [220.0, 261.63, 329.63];
+        let samples = (0..sample_rate * 2)
+            .map(|i| {
+                let t = i as f64 / sample_rate as f64;
+                frequencies.iter()
+                    .map(|frequency| (2.0 * std::f64::consts::PI * frequency * t).sin())
+                    .sum::<f64>() as f32
+            })
+            .collect();
+
+        let key = detect(&Signal { samples, sample_rate }).unwrap();
+        assert_eq!("Am", key.to_string());
+    }
+}
@@ -0,0 +1,59 @@
+use bytes::Bytes;
+use mp4ameta::{FreeformIdent, ImgFmt, Tag};
+use std::io::{Read, Seek};
+
+use crate::rekordbox::{Artwork, Color, Metadata};
+use super::{parse_bpm, parse_year, serato, whole_seconds};
+
+const ITUNES_MEAN: &str = "com.apple.iTunes";
+const SERATO_MEAN: &str = "com.serato.dj";
+
+fn freeform(tag: &Tag, name: &str) -> Option<String> {
+    freeform_of(tag, ITUNES_MEAN, name)
+}
+
+fn freeform_of(tag: &Tag, mean: &str, name: &str) -> Option<String> {
+    tag.strings_of(&FreeformIdent::new(mean, name))
+        .next()
+        .map(|value| value.to_string())
+}
+
+fn artwork(tag: &Tag) -> Option<Artwork> {
+    let image = tag.artwork()?;
+
+    Some(Artwork {
+        mime_type: match image.fmt {
+            ImgFmt::Bmp => "image/bmp",
+            ImgFmt::Jpeg => "image/jpeg",
+            ImgFmt::Png => "image/png",
+        }.to_string(),
+        data: Bytes::from(image.data.to_vec()),
+    })
+}
+
+/// M4A/AAC files keep their tags as iTunes style atoms below `moov.udta.meta.ilst`.
+pub fn read_metadata<R: Read + Seek>(reader: &mut R) -> Option<Metadata> {
+    let tag = Tag::read_from(reader).ok()?;
+
+    Some(Metadata {
+        artist: tag.artist().unwrap_or("").to_string(),
+        title: tag.title().unwrap_or("").to_string(),
+        // `tmpo` only holds whole numbers, some taggers keep the exact tempo in a freeform atom
+        bpm: freeform(&tag, "BPM")
+            .and_then(|bpm| parse_bpm(&bpm))
+            .or_else(|| tag.bpm().filter(|bpm| *bpm > 0).map(|bpm| bpm as u32 * 100)),
+        album: tag.album().unwrap_or("").to_string(),
+        duration: tag.duration().map(whole_seconds),
+        genre: tag.genre().unwrap_or("").to_string(),
+        key: freeform(&tag, "initialkey").unwrap_or_default(),
+        label: freeform(&tag, "LABEL").or_else(|| freeform(&tag, "publisher")).unwrap_or_default(),
+        year: tag.year().and_then(parse_year),
+        comment: tag.comment().unwrap_or("").to_string(),
+        rating: None,
+        color: freeform(&tag, "COLOR").and_then(|color| Color::from_name(&color)),
+        artwork: artwork(&tag),
+        cues: freeform_of(&tag, SERATO_MEAN, "markersv2")
+            .map(|markers| serato::markers_from_comment(&markers))
+            .unwrap_or_default(),
+    })
+}
@@ -0,0 +1,288 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::time::Duration;
+
+/// Upper bound of how far into the audio data we look for the first frame.
+const SYNC_SEARCH_WINDOW: usize = 64 * 1024;
+
+const ID3V1_SIZE: u64 = 128;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Version {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Layer {
+    Layer1,
+    Layer2,
+    Layer3,
+}
+
+#[derive(Debug, PartialEq)]
+struct FrameHeader {
+    version: Version,
+    layer: Layer,
+    bitrate: u32,
+    sample_rate: u32,
+    padding: bool,
+    mono: bool,
+}
+
+const BITRATES_V1_L1: [u32; 15] = [0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448];
+const BITRATES_V1_L2: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384];
+const BITRATES_V1_L3: [u32; 15] = [0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320];
+const BITRATES_V2_L1: [u32; 15] = [0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256];
+const BITRATES_V2_L23: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+impl FrameHeader {
+    fn parse(bytes: &[u8]) -> Option<FrameHeader> {
+        if bytes.len() < 4 || bytes[0] != 0xff || bytes[1] & 0xe0 != 0xe0 {
+            return None;
+        }
+
+        let version = match (bytes[1] >> 3) & 0x03 {
+            0b00 => Version::Mpeg25,
+            0b10 => Version::Mpeg2,
+            0b11 => Version::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (bytes[1] >> 1) & 0x03 {
+            0b01 => Layer::Layer3,
+            0b10 => Layer::Layer2,
+            0b11 => Layer::Layer1,
+            _ => return None,
+        };
+
+        let bitrate_index = (bytes[2] >> 4) as usize;
+        if bitrate_index == 0 || bitrate_index == 0x0f {
+            // Free format and invalid bitrates can't be used for frame length calculations.
+            return None;
+        }
+        let bitrates = match (version, layer) {
+            (Version::Mpeg1, Layer::Layer1) => &BITRATES_V1_L1,
+            (Version::Mpeg1, Layer::Layer2) => &BITRATES_V1_L2,
+            (Version::Mpeg1, Layer::Layer3) => &BITRATES_V1_L3,
+            (_, Layer::Layer1) => &BITRATES_V2_L1,
+            (_, _) => &BITRATES_V2_L23,
+        };
+
+        let sample_rate = match ((bytes[2] >> 2) & 0x03, version) {
+            (0, Version::Mpeg1) => 44100,
+            (1, Version::Mpeg1) => 48000,
+            (2, Version::Mpeg1) => 32000,
+            (0, Version::Mpeg2) => 22050,
+            (1, Version::Mpeg2) => 24000,
+            (2, Version::Mpeg2) => 16000,
+            (0, Version::Mpeg25) => 11025,
+            (1, Version::Mpeg25) => 12000,
+            (2, Version::Mpeg25) => 8000,
+            _ => return None,
+        };
+
+        Some(FrameHeader {
+            version,
+            layer,
+            bitrate: bitrates[bitrate_index] * 1000,
+            sample_rate,
+            padding: (bytes[2] >> 1) & 0x01 == 1,
+            mono: (bytes[3] >> 6) == 0x03,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.version) {
+            (Layer::Layer1, _) => 384,
+            (Layer::Layer2, _) => 1152,
+            (Layer::Layer3, Version::Mpeg1) => 1152,
+            (Layer::Layer3, _) => 576,
+        }
+    }
+
+    fn frame_length(&self) -> usize {
+        let padding = self.padding as u32;
+
+        (match self.layer {
+            Layer::Layer1 => (12 * self.bitrate / self.sample_rate + padding) * 4,
+            _ => self.samples_per_frame() / 8 * self.bitrate / self.sample_rate + padding,
+        }) as usize
+    }
+
+    /// Offset of the Xing/Info header, it's placed right after the side information.
+    fn xing_offset(&self) -> usize {
+        4 + match (self.version, self.mono) {
+            (Version::Mpeg1, false) => 32,
+            (Version::Mpeg1, true) => 17,
+            (_, false) => 17,
+            (_, true) => 9,
+        }
+    }
+
+    fn duration_of(&self, frames: u32) -> Duration {
+        Duration::from_secs_f64(
+            frames as f64 * self.samples_per_frame() as f64 / self.sample_rate as f64,
+        )
+    }
+}
+
+fn read_be_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let slice = bytes.get(offset..offset + 4)?;
+    Some(u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]))
+}
+
+/// Number of frames according to a Xing/Info (LAME) or VBRI header in the first frame.
+fn vbr_frame_count(header: &FrameHeader, frame: &[u8]) -> Option<u32> {
+    let xing_offset = header.xing_offset();
+    if let Some(tag) = frame.get(xing_offset..xing_offset + 4) {
+        if tag == b"Xing" || tag == b"Info" {
+            let flags = read_be_u32(frame, xing_offset + 4)?;
+            if flags & 0x01 == 0x01 {
+                return read_be_u32(frame, xing_offset + 8);
+            }
+            return None;
+        }
+    }
+
+    // VBRI is always located 32 bytes after the frame header.
+    if frame.get(36..40) == Some(&b"VBRI"[..]) {
+        return read_be_u32(frame, 36 + 14);
+    }
+
+    None
+}
+
+/// Size of a leading ID3v2 tag, including its header and optional footer.
+fn id3v2_size(header: &[u8]) -> u64 {
+    if header.len() < 10 || &header[0..3] != b"ID3" {
+        return 0;
+    }
+
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |acc, byte| (acc << 7) | (*byte & 0x7f) as u64);
+    let footer = if header[5] & 0x10 == 0x10 { 10 } else { 0 };
+
+    10 + size + footer
+}
+
+fn find_first_frame(buffer: &[u8]) -> Option<(usize, FrameHeader)> {
+    (0..buffer.len().saturating_sub(4)).find_map(|position| {
+        let header = FrameHeader::parse(&buffer[position..])?;
+
+        // Guard against false syncs inside garbage data by requiring the
+        // following frame to start where this one ends.
+        let next = position + header.frame_length();
+        match buffer.get(next..) {
+            Some(rest) if rest.len() >= 4 => FrameHeader::parse(rest).map(|_| (position, header)),
+            _ => Some((position, header)),
+        }
+    })
+}
+
+/// Calculate the playing time of an MPEG audio stream.
+///
+/// VBR files carry the total frame count in a Xing or VBRI header, for CBR files
+/// the length is derived from the bitrate and the size of the audio data.
+pub fn duration<R: Read + Seek>(reader: &mut R) -> Option<Duration> {
+    let file_size = reader.seek(SeekFrom::End(0)).ok()?;
+
+    let mut id3_header = [0u8; 10];
+    reader.seek(SeekFrom::Start(0)).ok()?;
+    let audio_start = match reader.read_exact(&mut id3_header) {
+        Ok(_) => id3v2_size(&id3_header),
+        Err(_) => 0,
+    };
+
+    let mut audio_end = file_size;
+    if file_size >= audio_start + ID3V1_SIZE {
+        let mut tag = [0u8; 3];
+        reader.seek(SeekFrom::Start(file_size - ID3V1_SIZE)).ok()?;
+        if reader.read_exact(&mut tag).is_ok() && &tag == b"TAG" {
+            audio_end -= ID3V1_SIZE;
+        }
+    }
+
+    let mut buffer = vec![];
+    reader.seek(SeekFrom::Start(audio_start)).ok()?;
+    reader.take(SYNC_SEARCH_WINDOW as u64).read_to_end(&mut buffer).ok()?;
+
+    let (position, header) = find_first_frame(&buffer)?;
+
+    if let Some(frames) = vbr_frame_count(&header, &buffer[position..]) {
+        return Some(header.duration_of(frames));
+    }
+
+    let audio_size = audio_end.checked_sub(audio_start + position as u64)?;
+    Some(Duration::from_secs_f64(audio_size as f64 * 8.0 / header.bitrate as f64))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    // MPEG-1 Layer III, 128 kbit/s, 44.1 kHz, joint stereo
+    const FRAME_HEADER: [u8; 4] = [0xff, 0xfb, 0x90, 0x40];
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut frame = FRAME_HEADER.to_vec();
+        frame.extend(payload);
+        frame.resize(417, 0);
+        frame
+    }
+
+    #[test]
+    fn it_parses_frame_headers() {
+        let header = FrameHeader::parse(&FRAME_HEADER).unwrap();
+
+        assert_eq!(Version::Mpeg1, header.version);
+        assert_eq!(Layer::Layer3, header.layer);
+        assert_eq!(128000, header.bitrate);
+        assert_eq!(44100, header.sample_rate);
+        assert_eq!(417, header.frame_length());
+        assert_eq!(None, FrameHeader::parse(&[0xff, 0xe9, 0x90, 0x40]));
+    }
+
+    #[test]
+    fn it_calculates_cbr_duration_from_the_audio_size() {
+        let mut file = vec![];
+        for _ in 0..100 {
+            file.extend(frame(&[]));
+        }
+
+        let duration = duration(&mut Cursor::new(file)).unwrap();
+        assert_eq!(2606, duration.as_millis());
+    }
+
+    #[test]
+    fn it_uses_the_xing_frame_count() {
+        let mut payload = vec![0u8; 32];
+        payload.extend(b"Xing");
+        payload.extend(&1u32.to_be_bytes());
+        payload.extend(&10000u32.to_be_bytes());
+
+        let mut file = b"ID3\x04\x00\x00\x00\x00\x00\x0a".to_vec();
+        file.extend(vec![0u8; 10]);
+        file.extend(frame(&payload));
+        file.extend(frame(&[]));
+
+        let duration = duration(&mut Cursor::new(file)).unwrap();
+        assert_eq!(261, duration.as_secs());
+    }
+
+    #[test]
+    fn it_uses_the_vbri_frame_count() {
+        let mut payload = vec![0u8; 32];
+        payload.extend(b"VBRI");
+        payload.extend(&[0x00, 0x01, 0x00, 0x00, 0x00, 0x4b]);
+        payload.extend(&400000u32.to_be_bytes());
+        payload.extend(&5000u32.to_be_bytes());
+
+        let mut file = frame(&payload);
+        file.extend(frame(&[]));
+
+        let duration = duration(&mut Cursor::new(file)).unwrap();
+        assert_eq!(130, duration.as_secs());
+    }
+}
@@ -0,0 +1,183 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use walkdir::WalkDir;
+
+/// A playlist file found below the library root.
+#[derive(Debug, PartialEq)]
+pub struct PlaylistFile {
+    /// Folders between the library root and the playlist, outermost first.
+    pub folders: Vec<String>,
+    pub name: String,
+    /// Audio files in playlist order, resolved against the playlist location.
+    pub entries: Vec<PathBuf>,
+}
+
+fn is_playlist(path: &Path) -> bool {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) => {
+            let extension = extension.to_lowercase();
+            extension == "m3u" || extension == "m3u8" || extension == "pls"
+        },
+        None => false,
+    }
+}
+
+/// Resolve `.` and `..` without touching the filesystem, so playlist entries and
+/// scanned tracks can be compared even when the files are symlinked.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => {
+                normalized.pop();
+            },
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+
+    while index < bytes.len() {
+        let escaped = match (bytes[index], bytes.get(index + 1..index + 3)) {
+            (b'%', Some(hex)) => std::str::from_utf8(hex).ok().and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+            _ => None,
+        };
+
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            },
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            },
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn resolve_entry(base: &Path, entry: &str) -> Option<PathBuf> {
+    let entry = entry.trim();
+    if entry.is_empty() {
+        return None;
+    }
+
+    let entry = match entry.strip_prefix("file://") {
+        Some(uri) => percent_decode(uri),
+        // Remote streams can't be served to the players.
+        None if entry.contains("://") => return None,
+        None => entry.replace('\\', "/"),
+    };
+
+    Some(normalize_path(&base.join(entry)))
+}
+
+fn parse_m3u(base: &Path, content: &str) -> Vec<PathBuf> {
+    content
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}'))
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| resolve_entry(base, line))
+        .collect()
+}
+
+fn parse_pls(base: &Path, content: &str) -> Vec<PathBuf> {
+    let mut entries = content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            let number = key.strip_prefix("File")?.parse::<u32>().ok()?;
+            Some((number, resolve_entry(base, value)?))
+        })
+        .collect::<Vec<(u32, PathBuf)>>();
+
+    entries.sort_by_key(|(number, _)| *number);
+    entries.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Read a single playlist. M3U files without the m3u8 extension are often
+/// latin1 encoded, invalid UTF-8 is replaced rather than rejected.
+pub fn read_playlist(path: &Path) -> Option<Vec<PathBuf>> {
+    let content = String::from_utf8_lossy(&fs::read(path).ok()?).to_string();
+    let base = path.parent().unwrap_or_else(|| Path::new(""));
+    let extension = path.extension()?.to_str()?.to_lowercase();
+
+    Some(match extension.as_str() {
+        "pls" => parse_pls(base, &content),
+        _ => parse_m3u(base, &content),
+    })
+}
+
+/// Find every playlist below `root`, folders between the root and the playlist
+/// become playlist folders.
+pub fn scan_playlists<T: AsRef<Path>>(root: T) -> Vec<PlaylistFile> {
+    let root = root.as_ref();
+
+    WalkDir::new(root)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+        .into_iter()
+        .filter_entry(|entry| !entry.file_name().to_str().map(|name| name.starts_with('.')).unwrap_or(false))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_playlist(entry.path()))
+        .filter_map(|entry| {
+            let folders = entry.path()
+                .parent()?
+                .strip_prefix(root)
+                .ok()?
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy().to_string())
+                .collect();
+
+            Some(PlaylistFile {
+                folders,
+                name: entry.path().file_stem()?.to_string_lossy().to_string(),
+                entries: read_playlist(entry.path())?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_extended_m3u() {
+        let content = "#EXTM3U\n#EXTINF:123,Loopmasters - Demo Track 1\nDemo Track 1.mp3\n\n../Other/Demo Track 2.flac\nhttp://example.com/stream\n";
+
+        assert_eq!(vec![
+            PathBuf::from("/music/House/Demo Track 1.mp3"),
+            PathBuf::from("/music/Other/Demo Track 2.flac"),
+        ], parse_m3u(Path::new("/music/House"), content));
+    }
+
+    #[test]
+    fn it_parses_windows_paths_and_file_uris() {
+        let content = "Sub\\Demo Track 1.mp3\nfile:///music/Demo%20Track%202.mp3\n";
+
+        assert_eq!(vec![
+            PathBuf::from("/music/Sub/Demo Track 1.mp3"),
+            PathBuf::from("/music/Demo Track 2.mp3"),
+        ], parse_m3u(Path::new("/music"), content));
+    }
+
+    #[test]
+    fn it_parses_pls_in_entry_order() {
+        let content = "[playlist]\nFile2=b.mp3\nTitle2=B\nFile1=/abs/a.mp3\nNumberOfEntries=2\nVersion=2\n";
+
+        assert_eq!(vec![
+            PathBuf::from("/abs/a.mp3"),
+            PathBuf::from("/music/b.mp3"),
+        ], parse_pls(Path::new("/music"), content));
+    }
+}
@@ -0,0 +1,122 @@
+use percent_encoding::percent_decode_str;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::rekordbox::{Cue, CueKind};
+use super::playlist::normalize_path;
+
+/// Name of the collection export rekordbox writes, picked up from the library roots.
+pub const COLLECTION_FILE: &str = "rekordbox.xml";
+
+/// POSITION_MARK types, fades and other markers aren't cues players can use.
+const TYPE_CUE: &str = "0";
+const TYPE_LOOP: &str = "4";
+
+/// Locations are file URLs, "file://localhost/Music/Track%201.mp3".
+fn location_path(location: &str) -> Option<PathBuf> {
+    let path = location.strip_prefix("file://localhost")
+        .or_else(|| location.strip_prefix("file://"))?;
+
+    let decoded = percent_decode_str(path).decode_utf8().ok()?;
+    Some(normalize_path(Path::new(decoded.as_ref())))
+}
+
+/// Start and end are seconds with three decimals.
+fn milliseconds(seconds: &str) -> Option<u32> {
+    let seconds = seconds.trim().parse::<f64>().ok()?;
+
+    Some((seconds * 1000.0).round() as u32)
+}
+
+fn position_mark(mark: roxmltree::Node) -> Option<Cue> {
+    let end = match mark.attribute("Type")? {
+        TYPE_CUE => None,
+        TYPE_LOOP => Some(milliseconds(mark.attribute("End")?)?),
+        _ => return None,
+    };
+
+    let kind = match mark.attribute("Num")?.parse::<i8>().ok()? {
+        number @ 0..=7 => CueKind::HotCue(number as u8),
+        _ => CueKind::Memory,
+    };
+
+    let color = ["Red", "Green", "Blue"].iter()
+        .map(|name| mark.attribute(*name).and_then(|value| value.parse::<u8>().ok()))
+        .collect::<Option<Vec<u8>>>()
+        .map(|rgb| [rgb[0], rgb[1], rgb[2]]);
+
+    Some(Cue {
+        kind,
+        start: milliseconds(mark.attribute("Start")?)?,
+        end,
+        color,
+        comment: mark.attribute("Name").unwrap_or("").to_string(),
+    })
+}
+
+/// Cue points of the tracks in a rekordbox collection export, by path.
+pub fn parse_collection(xml: &str) -> HashMap<PathBuf, Vec<Cue>> {
+    let document = match roxmltree::Document::parse(xml) {
+        Ok(document) => document,
+        Err(err) => {
+            eprintln!("Ignoring unreadable rekordbox collection; error = {}", err);
+            return HashMap::new();
+        },
+    };
+
+    document.descendants()
+        .filter(|node| node.has_tag_name("TRACK"))
+        .filter_map(|track| {
+            let path = location_path(track.attribute("Location")?)?;
+            let cues = track.children()
+                .filter(|node| node.has_tag_name("POSITION_MARK"))
+                .filter_map(position_mark)
+                .collect::<Vec<Cue>>();
+
+            if cues.is_empty() { None } else { Some((path, cues)) }
+        })
+        .collect()
+}
+
+/// Cue points from the rekordbox collection export in `root`, if there is one.
+pub fn read_collection_cues(root: &Path) -> HashMap<PathBuf, Vec<Cue>> {
+    match std::fs::read_to_string(root.join(COLLECTION_FILE)) {
+        Ok(xml) => parse_collection(&xml),
+        Err(_) => HashMap::new(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_reads_cues_and_loops_from_a_collection_export() {
+        let cues = parse_collection(r#"<?xml version="1.0" encoding="UTF-8"?>
+            <DJ_PLAYLISTS Version="1.0.0">
+              <COLLECTION Entries="2">
+                <TRACK TrackID="1" Name="Demo" Location="file://localhost/music/Demo%20Track.mp3">
+                  <TEMPO Inizio="0.025" Bpm="128.00" Metro="4/4" Battito="1"/>
+                  <POSITION_MARK Name="" Type="0" Start="0.025" Num="-1"/>
+                  <POSITION_MARK Name="Drop" Type="0" Start="61.900" Num="1" Red="40" Green="226" Blue="20"/>
+                  <POSITION_MARK Name="Intro" Type="4" Start="0.025" End="7.525" Num="-1"/>
+                  <POSITION_MARK Name="" Type="1" Start="2.000" Num="-1"/>
+                </TRACK>
+                <TRACK TrackID="2" Name="No cues" Location="file://localhost/music/Other.mp3"/>
+              </COLLECTION>
+            </DJ_PLAYLISTS>"#);
+
+        assert_eq!(1, cues.len());
+        assert_eq!(Some(&vec![
+            Cue { kind: CueKind::Memory, start: 25, end: None, color: None, comment: "".to_string() },
+            Cue {
+                kind: CueKind::HotCue(1),
+                start: 61900,
+                end: None,
+                color: Some([40, 226, 20]),
+                comment: "Drop".to_string(),
+            },
+            Cue { kind: CueKind::Memory, start: 25, end: Some(7525), color: None, comment: "Intro".to_string() },
+        ]), cues.get(Path::new("/music/Demo Track.mp3")));
+    }
+}
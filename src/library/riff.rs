@@ -0,0 +1,339 @@
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt};
+use id3::Tag;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::Duration;
+
+use crate::rekordbox::Metadata;
+use super::{extract_id3v2, parse_year, whole_seconds};
+
+/// WAV files are RIFF containers with little endian chunk sizes while AIFF files
+/// use the big endian IFF layout. Apart from that the chunk structure is the same.
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Container {
+    Riff,
+    Iff,
+}
+
+#[derive(Debug, PartialEq)]
+struct Chunk {
+    id: [u8; 4],
+    offset: u64,
+    size: u32,
+}
+
+impl Chunk {
+    fn is(&self, id: &[u8; 4]) -> bool {
+        self.id.eq_ignore_ascii_case(id)
+    }
+}
+
+fn read_container<R: Read>(reader: &mut R) -> io::Result<Container> {
+    let mut header = [0u8; 12];
+    reader.read_exact(&mut header)?;
+
+    match (&header[0..4], &header[8..12]) {
+        (b"RIFF", b"WAVE") => Ok(Container::Riff),
+        (b"FORM", b"AIFF") | (b"FORM", b"AIFC") => Ok(Container::Iff),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Not a RIFF/IFF audio file")),
+    }
+}
+
+fn read_size<R: Read>(reader: &mut R, container: Container) -> io::Result<u32> {
+    match container {
+        Container::Riff => reader.read_u32::<LittleEndian>(),
+        Container::Iff => reader.read_u32::<BigEndian>(),
+    }
+}
+
+fn read_chunks<R: Read + Seek>(reader: &mut R, container: Container) -> io::Result<Vec<Chunk>> {
+    let mut chunks = vec![];
+
+    loop {
+        let mut id = [0u8; 4];
+        match reader.read_exact(&mut id) {
+            Ok(_) => {},
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        // Stray bytes after the last chunk aren't a chunk header.
+        let size = match read_size(reader, container) {
+            Ok(size) => size,
+            Err(ref err) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        let offset = reader.stream_position()?;
+
+        chunks.push(Chunk { id, offset, size });
+
+        // Chunks are padded to an even number of bytes.
+        let padded_size = size as u64 + (size as u64 & 1);
+        reader.seek(SeekFrom::Start(offset + padded_size))?;
+    }
+
+    Ok(chunks)
+}
+
+/// Tag chunks hold embedded artwork at most, larger ones are corrupt.
+const MAX_TAG_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+fn read_chunk_data<R: Read + Seek>(reader: &mut R, chunk: &Chunk) -> io::Result<Vec<u8>> {
+    if chunk.size > MAX_TAG_CHUNK_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Chunk too large to hold tags"));
+    }
+
+    // The size is only trusted as far as the file actually goes.
+    let mut data = vec![];
+    reader.seek(SeekFrom::Start(chunk.offset))?;
+    reader.take(chunk.size as u64).read_to_end(&mut data)?;
+
+    if data.len() != chunk.size as usize {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Chunk exceeds the file"));
+    }
+
+    Ok(data)
+}
+
+/// WAV: the `data` chunk size divided by the byte rate from the `fmt ` chunk.
+fn wave_duration<R: Read + Seek>(reader: &mut R, chunks: &[Chunk]) -> Option<Duration> {
+    let format = chunks.iter().find(|chunk| chunk.is(b"fmt "))?;
+    let data = chunks.iter().find(|chunk| chunk.is(b"data"))?;
+
+    reader.seek(SeekFrom::Start(format.offset + 8)).ok()?;
+    let byte_rate = reader.read_u32::<LittleEndian>().ok()?;
+    if byte_rate == 0 {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(data.size as f64 / byte_rate as f64))
+}
+
+/// Decode the 80 bit IEEE 754 extended precision float used for AIFF sample rates.
+fn extended_to_f64(bytes: &[u8; 10]) -> f64 {
+    let exponent = (u16::from_be_bytes([bytes[0], bytes[1]]) & 0x7fff) as i32;
+    let mut mantissa = [0u8; 8];
+    mantissa.copy_from_slice(&bytes[2..10]);
+    let mantissa = u64::from_be_bytes(mantissa);
+
+    if exponent == 0 && mantissa == 0 {
+        return 0.0;
+    }
+
+    mantissa as f64 * 2f64.powi(exponent - 16383 - 63)
+}
+
+/// AIFF: number of sample frames and the sample rate from the `COMM` chunk.
+fn aiff_duration<R: Read + Seek>(reader: &mut R, chunks: &[Chunk]) -> Option<Duration> {
+    let common = chunks.iter().find(|chunk| chunk.is(b"COMM"))?;
+
+    reader.seek(SeekFrom::Start(common.offset + 2)).ok()?;
+    let sample_frames = reader.read_u32::<BigEndian>().ok()?;
+    let _sample_size = reader.read_u16::<BigEndian>().ok()?;
+    let mut sample_rate = [0u8; 10];
+    reader.read_exact(&mut sample_rate).ok()?;
+    let sample_rate = extended_to_f64(&sample_rate);
+    if sample_rate <= 0.0 {
+        return None;
+    }
+
+    Some(Duration::from_secs_f64(sample_frames as f64 / sample_rate))
+}
+
+fn info_string(value: &[u8]) -> String {
+    String::from_utf8_lossy(value)
+        .trim_end_matches('\u{0}')
+        .trim()
+        .to_string()
+}
+
+/// Parse a `LIST` chunk of type `INFO`, the native WAV way of storing tags.
+fn extract_list_info(data: &[u8]) -> Option<Metadata> {
+    if data.len() < 4 || &data[0..4] != b"INFO" {
+        return None;
+    }
+
+    let mut metadata = Metadata::default();
+    let mut position = 4;
+
+    while position + 8 <= data.len() {
+        let id = &data[position..position + 4];
+        let size = (&data[position + 4..position + 8]).read_u32::<LittleEndian>().ok()? as usize;
+        let start = position + 8;
+        let end = std::cmp::min(start + size, data.len());
+        let value = info_string(&data[start..end]);
+
+        match id {
+            b"INAM" => metadata.title = value,
+            b"IART" => metadata.artist = value,
+            b"IPRD" => metadata.album = value,
+            b"IGNR" => metadata.genre = value,
+            b"ICMT" => metadata.comment = value,
+            b"ICRD" => metadata.year = parse_year(&value),
+            _ => {},
+        };
+
+        position = start + size + (size & 1);
+    }
+
+    Some(metadata)
+}
+
+fn read_tags<R: Read + Seek>(reader: &mut R, chunks: &[Chunk]) -> Metadata {
+    if let Some(chunk) = chunks.iter().find(|chunk| chunk.is(b"id3 ")) {
+        if let Ok(data) = read_chunk_data(reader, chunk) {
+            if let Ok(tag) = Tag::read_from(&data[..]) {
+                return extract_id3v2(tag);
+            }
+        }
+    }
+
+    chunks
+        .iter()
+        .filter(|chunk| chunk.is(b"LIST"))
+        .filter_map(|chunk| read_chunk_data(reader, chunk).ok())
+        .find_map(|data| extract_list_info(&data))
+        .unwrap_or_default()
+}
+
+pub fn read_metadata<R: Read + Seek>(reader: &mut R) -> Option<Metadata> {
+    let container = read_container(reader).ok()?;
+    let chunks = read_chunks(reader, container).ok()?;
+
+    let mut metadata = read_tags(reader, &chunks);
+    metadata.duration = match container {
+        Container::Riff => wave_duration(reader, &chunks),
+        Container::Iff => aiff_duration(reader, &chunks),
+    }.map(whole_seconds);
+
+    Some(metadata)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn riff_chunk(id: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend(&(data.len() as u32).to_le_bytes());
+        chunk.extend(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn wave_file(chunks: Vec<Vec<u8>>) -> Cursor<Vec<u8>> {
+        let body = chunks.concat();
+        let mut file = b"RIFF".to_vec();
+        file.extend(&(body.len() as u32 + 4).to_le_bytes());
+        file.extend(b"WAVE");
+        file.extend(body);
+        Cursor::new(file)
+    }
+
+    #[test]
+    fn it_reads_list_info_tags_from_wave_files() {
+        let mut info = b"INFO".to_vec();
+        info.extend(riff_chunk(b"INAM", b"Demo Track\0"));
+        info.extend(riff_chunk(b"IART", b"Loopmasters\0"));
+
+        let mut file = wave_file(vec![
+            riff_chunk(b"fmt ", &[0u8; 16]),
+            riff_chunk(b"LIST", &info),
+            riff_chunk(b"data", &[0u8; 3]),
+        ]);
+
+        assert_eq!(Some(Metadata {
+            artist: "Loopmasters".to_string(),
+            title: "Demo Track".to_string(),
+            ..Default::default()
+        }), read_metadata(&mut file));
+    }
+
+    #[test]
+    fn it_prefers_id3_chunks() {
+        let mut tag = Tag::new();
+        tag.set_artist("Loopmasters");
+        tag.set_title("Tagged Track");
+        let mut id3_data = vec![];
+        tag.write_to(&mut id3_data, id3::Version::Id3v24).unwrap();
+
+        let mut info = b"INFO".to_vec();
+        info.extend(riff_chunk(b"INAM", b"Info Track\0"));
+
+        let mut file = wave_file(vec![
+            riff_chunk(b"LIST", &info),
+            riff_chunk(b"id3 ", &id3_data),
+        ]);
+
+        assert_eq!("Tagged Track", read_metadata(&mut file).unwrap().title);
+    }
+
+    #[test]
+    fn it_calculates_wave_duration() {
+        let mut format = vec![0x01, 0x00, 0x02, 0x00];
+        format.extend(&44100u32.to_le_bytes());
+        format.extend(&(44100u32 * 4).to_le_bytes());
+        format.extend(&[0x04, 0x00, 0x10, 0x00]);
+
+        let mut file = wave_file(vec![
+            riff_chunk(b"fmt ", &format),
+            riff_chunk(b"data", &vec![0u8; 44100 * 4 * 3]),
+        ]);
+
+        assert_eq!(Some(3), read_metadata(&mut file).unwrap().duration);
+    }
+
+    #[test]
+    fn it_calculates_aiff_duration() {
+        let mut common = vec![0x00, 0x02];
+        common.extend(&(48000u32 * 5).to_be_bytes());
+        common.extend(&[0x00, 0x10]);
+        // 48000 as 80 bit extended float
+        common.extend(&[0x40, 0x0e, 0xbb, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]);
+
+        let mut chunk = b"COMM".to_vec();
+        chunk.extend(&(common.len() as u32).to_be_bytes());
+        chunk.extend(common);
+
+        let mut file = b"FORM".to_vec();
+        file.extend(&(chunk.len() as u32 + 4).to_be_bytes());
+        file.extend(b"AIFF");
+        file.extend(chunk);
+
+        assert_eq!(Some(5), read_metadata(&mut Cursor::new(file)).unwrap().duration);
+    }
+
+    #[test]
+    fn it_ignores_stray_bytes_after_the_last_chunk() {
+        let mut info = b"INFO".to_vec();
+        info.extend(riff_chunk(b"INAM", b"Demo Track\0"));
+
+        for tail in 2..=7 {
+            let mut file = wave_file(vec![riff_chunk(b"LIST", &info)]).into_inner();
+            file.extend(vec![0xffu8; tail]);
+
+            assert_eq!("Demo Track", read_metadata(&mut Cursor::new(file)).unwrap().title);
+        }
+    }
+
+    #[test]
+    fn it_skips_chunks_larger_than_the_file() {
+        let mut info = b"INFO".to_vec();
+        info.extend(riff_chunk(b"INAM", b"Demo Track\0"));
+
+        let mut corrupt = b"id3 ".to_vec();
+        corrupt.extend(&u32::MAX.to_le_bytes());
+        corrupt.extend(&[0u8; 8]);
+
+        let mut file = wave_file(vec![riff_chunk(b"LIST", &info), corrupt]);
+
+        assert_eq!("Demo Track", read_metadata(&mut file).unwrap().title);
+    }
+
+    #[test]
+    fn it_rejects_unknown_containers() {
+        assert_eq!(None, read_metadata(&mut Cursor::new(b"OggS0000000000".to_vec())));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::rekordbox::{Cue, CueKind};
+
+/// Description of the GEOB frame, and the object in the base64 comments of
+/// other formats, Serato DJ keeps its cue points and loops in.
+pub const MARKERS_DESCRIPTION: &str = "Serato Markers2";
+
+/// Serato wraps its base64 at 72 characters and doesn't always pad or even
+/// finish the last group, everything that can be decoded is kept.
+fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut text = text.iter()
+        .copied()
+        .filter(|byte| byte.is_ascii_alphanumeric() || *byte == b'+' || *byte == b'/')
+        .collect::<Vec<u8>>();
+
+    if text.len() % 4 == 1 {
+        text.pop();
+    }
+
+    base64::decode_config(&text, base64::STANDARD_NO_PAD).ok()
+}
+
+fn nul_terminated(data: &[u8]) -> (&[u8], &[u8]) {
+    match data.iter().position(|byte| *byte == 0) {
+        Some(end) => (&data[..end], &data[end + 1..]),
+        None => (data, &[]),
+    }
+}
+
+fn be_u32(data: &[u8]) -> u32 {
+    u32::from_be_bytes([data[0], data[1], data[2], data[3]])
+}
+
+/// `CUE` entries: index, position, RGB colour and name.
+fn cue_entry(data: &[u8]) -> Option<Cue> {
+    if data.len() < 13 {
+        return None;
+    }
+
+    Some(Cue {
+        kind: CueKind::HotCue(data[1]),
+        start: be_u32(&data[2..6]),
+        end: None,
+        color: Some([data[7], data[8], data[9]]),
+        comment: String::from_utf8_lossy(nul_terminated(&data[12..]).0).to_string(),
+    })
+}
+
+/// `LOOP` entries: index, start, end, ARGB colour, locked flag and name.
+/// Serato keeps saved loops apart from its hot cues, they become memory loops.
+fn loop_entry(data: &[u8]) -> Option<Cue> {
+    if data.len() < 21 {
+        return None;
+    }
+
+    Some(Cue {
+        kind: CueKind::Memory,
+        start: be_u32(&data[2..6]),
+        end: Some(be_u32(&data[6..10])),
+        color: None,
+        comment: String::from_utf8_lossy(nul_terminated(&data[20..]).0).to_string(),
+    })
+}
+
+/// Parse the "Serato Markers2" object: a version followed by base64 holding
+/// another version and NUL terminated entry names, each with a big endian
+/// length and its data.
+pub fn markers(object: &[u8]) -> Vec<Cue> {
+    let decoded = match object.get(2..).and_then(decode_base64) {
+        Some(decoded) => decoded,
+        None => return vec![],
+    };
+
+    let mut cues = vec![];
+    let mut rest = decoded.get(2..).unwrap_or(&[]);
+    while !rest.is_empty() {
+        let (name, after_name) = nul_terminated(rest);
+        if name.is_empty() || after_name.len() < 4 {
+            break;
+        }
+
+        let length = be_u32(after_name) as usize;
+        let data = match after_name.get(4..4 + length) {
+            Some(data) => data,
+            None => break,
+        };
+        rest = &after_name[4 + length..];
+
+        let cue = match name {
+            b"CUE" => cue_entry(data),
+            b"LOOP" => loop_entry(data),
+            _ => None,
+        };
+        cues.extend(cue);
+    }
+
+    cues
+}
+
+/// FLAC and MP4 files hold the markers as base64 of the whole GEOB frame body:
+/// a MIME type, a file name and the description before the object itself.
+pub fn markers_from_comment(text: &str) -> Vec<Cue> {
+    let body = match decode_base64(text.as_bytes()) {
+        Some(body) => body,
+        None => return vec![],
+    };
+
+    let mut description = MARKERS_DESCRIPTION.as_bytes().to_vec();
+    description.push(0);
+
+    match body.windows(description.len()).position(|window| window == &description[..]) {
+        Some(start) => markers(&body[start + description.len()..]),
+        None => vec![],
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut entry = name.as_bytes().to_vec();
+        entry.push(0);
+        entry.extend(&(data.len() as u32).to_be_bytes());
+        entry.extend(data);
+        entry
+    }
+
+    fn markers_object() -> Vec<u8> {
+        let mut cue = vec![0x00, 0x02];
+        cue.extend(&61_000u32.to_be_bytes());
+        cue.extend(&[0x00, 0xcc, 0x00, 0x00, 0x00, 0x00]);
+        cue.extend(b"Drop\0");
+
+        let mut saved_loop = vec![0x00, 0x00];
+        saved_loop.extend(&1_000u32.to_be_bytes());
+        saved_loop.extend(&8_500u32.to_be_bytes());
+        saved_loop.extend(&[0xff, 0xff, 0xff, 0xff, 0x00, 0xff, 0x27, 0xaa, 0xe1, 0x00]);
+        saved_loop.extend(b"\0");
+
+        let mut payload = vec![0x01, 0x01];
+        payload.extend(entry("COLOR", &[0x00, 0xff, 0xff, 0xff]));
+        payload.extend(entry("CUE", &cue));
+        payload.extend(entry("LOOP", &saved_loop));
+        payload.push(0);
+
+        // Serato leaves off the padding and breaks lines
+        let mut encoded = base64::encode_config(&payload, base64::STANDARD_NO_PAD).into_bytes();
+        encoded.insert(72, b'\n');
+
+        let mut object = vec![0x01, 0x01];
+        object.extend(encoded);
+        object
+    }
+
+    #[test]
+    fn it_reads_hot_cues_and_saved_loops() {
+        assert_eq!(vec![
+            Cue {
+                kind: CueKind::HotCue(2),
+                start: 61_000,
+                end: None,
+                color: Some([0xcc, 0x00, 0x00]),
+                comment: "Drop".to_string(),
+            },
+            Cue {
+                kind: CueKind::Memory,
+                start: 1_000,
+                end: Some(8_500),
+                color: None,
+                comment: "".to_string(),
+            },
+        ], markers(&markers_object()));
+    }
+
+    #[test]
+    fn it_reads_markers_from_base64_comments() {
+        let mut body = b"application/octet-stream\0\0Serato Markers2\0".to_vec();
+        body.extend(markers_object());
+
+        let cues = markers_from_comment(&base64::encode(&body));
+        assert_eq!(2, cues.len());
+        assert_eq!(CueKind::HotCue(2), cues[0].kind);
+    }
+
+    #[test]
+    fn it_ignores_garbage() {
+        assert!(markers(b"\x01\x01!!!").is_empty());
+        assert!(markers_from_comment("not markers").is_empty());
+    }
+}
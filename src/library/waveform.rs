@@ -0,0 +1,204 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+
+use super::decode::Signal;
+
+/// Columns across the width of the player's preview waveform.
+pub const PREVIEW_COLUMNS: usize = 400;
+
+/// Columns of the tiny preview following the regular one.
+const TINY_COLUMNS: usize = 100;
+
+/// Entries per second of the detailed waveform, one per half frame of the
+/// 75 frames a CD second is made of.
+pub const DETAIL_ENTRIES_PER_SECOND: u32 = 150;
+
+/// Bytes preceding the entries of the detailed waveform, players skip them.
+const DETAIL_LEADING_BYTES: usize = 19;
+
+const MAX_HEIGHT: u8 = 31;
+const MAX_TINY_HEIGHT: u8 = 15;
+const MAX_WHITENESS: u8 = 7;
+
+/// The waveform shown across the width of the player, one height (0-31) and
+/// whiteness (0-7) per column.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PreviewWaveform {
+    columns: Vec<(u8, u8)>,
+}
+
+/// A flat line, for tracks whose audio can't be decoded.
+impl Default for PreviewWaveform {
+    fn default() -> Self {
+        Self {
+            columns: vec![(0, 0); PREVIEW_COLUMNS],
+        }
+    }
+}
+
+impl PreviewWaveform {
+    pub fn analyse(signal: &Signal) -> Self {
+        let levels = (0..PREVIEW_COLUMNS)
+            .map(|column| {
+                let start = column * signal.samples.len() / PREVIEW_COLUMNS;
+                let end = (column + 1) * signal.samples.len() / PREVIEW_COLUMNS;
+
+                level(&signal.samples[start..end])
+            })
+            .collect::<Vec<(f32, f32)>>();
+
+        Self {
+            columns: heights_and_whiteness(levels),
+        }
+    }
+
+    /// The payload of the preview waveform response: the columns as height
+    /// and whiteness bytes, the tiny preview and four trailing bytes.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(2 * PREVIEW_COLUMNS + TINY_COLUMNS + 4);
+
+        for (height, whiteness) in &self.columns {
+            bytes.put_u8(*height);
+            bytes.put_u8(*whiteness);
+        }
+
+        for chunk in self.columns.chunks(PREVIEW_COLUMNS / TINY_COLUMNS) {
+            let height = chunk.iter().map(|(height, _)| *height).max().unwrap_or(0) as u16;
+            bytes.put_u8((height * MAX_TINY_HEIGHT as u16 / MAX_HEIGHT as u16) as u8);
+        }
+
+        bytes.put_u32(0);
+
+        bytes.freeze()
+    }
+}
+
+/// The scrolling waveform shown while playing, one height (0-31) and
+/// whiteness (0-7) per half frame.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct DetailWaveform {
+    entries: Vec<(u8, u8)>,
+}
+
+impl DetailWaveform {
+    pub fn analyse(signal: &Signal) -> Self {
+        // Boundaries are computed per entry, rounding the entry length would
+        // make the waveform drift away from the audio.
+        let rate = signal.sample_rate as usize;
+        let per_second = DETAIL_ENTRIES_PER_SECOND as usize;
+        let count = (signal.samples.len() * per_second).div_ceil(rate);
+        let levels = (0..count)
+            .map(|entry| {
+                let start = entry * rate / per_second;
+                let end = std::cmp::min((entry + 1) * rate / per_second, signal.samples.len());
+
+                level(&signal.samples[start..end])
+            })
+            .collect();
+
+        Self {
+            entries: heights_and_whiteness(levels),
+        }
+    }
+
+    /// The payload of the detailed waveform response, each entry packs the
+    /// whiteness into the upper three bits and the height into the lower five.
+    pub fn to_bytes(&self) -> Bytes {
+        let mut bytes = BytesMut::with_capacity(DETAIL_LEADING_BYTES + self.entries.len());
+
+        bytes.put_slice(&[0; DETAIL_LEADING_BYTES]);
+        for (height, whiteness) in &self.entries {
+            bytes.put_u8(whiteness << 5 | height);
+        }
+
+        bytes.freeze()
+    }
+}
+
+/// Heights relative to the loudest part of the track, so quiet masters still
+/// fill the display.
+fn heights_and_whiteness(levels: Vec<(f32, f32)>) -> Vec<(u8, u8)> {
+    let loudest = levels.iter().fold(0.0f32, |loudest, (rms, _)| loudest.max(*rms));
+
+    levels.into_iter()
+        .map(|(rms, brightness)| {
+            let height = if loudest > 0.0 { rms / loudest } else { 0.0 };
+
+            (scale(height, MAX_HEIGHT), scale(brightness, MAX_WHITENESS))
+        })
+        .collect()
+}
+
+/// Loudness (RMS) and brightness (0-1) of a stretch of audio. Brightness is
+/// the energy of the signal's slope relative to the signal itself, which
+/// grows with frequency, turned into a 0-1 range covering the audible spread.
+fn level(samples: &[f32]) -> (f32, f32) {
+    if samples.len() < 2 {
+        return (0.0, 0.0);
+    }
+
+    let energy: f32 = samples.iter().map(|sample| sample * sample).sum();
+    let slope: f32 = samples.windows(2).map(|pair| (pair[1] - pair[0]).powi(2)).sum();
+    let rms = (energy / samples.len() as f32).sqrt();
+
+    // The slope carries up to 4 times the energy of a signal at the Nyquist frequency.
+    let brightness = if energy > 0.0 { (slope / energy / 4.0).sqrt() } else { 0.0 };
+
+    (rms, brightness.min(1.0))
+}
+
+fn scale(value: f32, max: u8) -> u8 {
+    (value.clamp(0.0, 1.0) * max as f32).round() as u8
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tone(frequency: f32, amplitude: f32, seconds: usize) -> Vec<f32> {
+        let sample_rate = 11025;
+        (0..seconds * sample_rate)
+            .map(|n| amplitude * (2.0 * std::f32::consts::PI * frequency * n as f32 / sample_rate as f32).sin())
+            .collect()
+    }
+
+    #[test]
+    fn it_follows_the_loudness_and_brightness_of_the_audio() {
+        let mut samples = tone(60.0, 0.2, 20);
+        samples.extend(tone(4000.0, 0.8, 20));
+
+        let waveform = PreviewWaveform::analyse(&Signal { samples, sample_rate: 11025 });
+        let (quiet_height, dark) = waveform.columns[100];
+        let (loud_height, bright) = waveform.columns[300];
+
+        assert_eq!(loud_height, MAX_HEIGHT);
+        assert!(quiet_height < 10);
+        assert!(dark < bright);
+
+        let bytes = waveform.to_bytes();
+        assert_eq!(bytes.len(), 904);
+        assert_eq!(&bytes[600..602], &[loud_height, bright]);
+        assert_eq!(bytes[800 + 75], MAX_TINY_HEIGHT);
+    }
+
+    #[test]
+    fn it_packs_half_frames_of_the_detailed_waveform() {
+        let mut samples = tone(60.0, 0.2, 1);
+        samples.extend(tone(4000.0, 0.8, 1));
+
+        let waveform = DetailWaveform::analyse(&Signal { samples, sample_rate: 11025 });
+        let bytes = waveform.to_bytes();
+
+        assert_eq!(waveform.entries.len(), 2 * 150);
+        assert_eq!(bytes.len(), 19 + waveform.entries.len());
+        assert_eq!(bytes[19 + 200] & 0x1f, MAX_HEIGHT);
+        assert!(bytes[19 + 50] >> 5 < bytes[19 + 200] >> 5);
+    }
+
+    #[test]
+    fn it_draws_silence_flat() {
+        let waveform = PreviewWaveform::analyse(&Signal { samples: vec![0.0; 1000], sample_rate: 11025 });
+
+        assert!(waveform.to_bytes().iter().all(|byte| *byte == 0));
+    }
+}
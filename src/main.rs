@@ -3,12 +3,16 @@ mod utils;
 mod component;
 mod rpc;
 mod library;
+mod config;
 
 use component::App;
+use config::Config;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut app = App::new("/home/jonas/Music/TermDJ");
+    let config = Config::from_args(std::env::args().skip(1))?;
+
+    let mut app = App::new(config.roots()?, config.scan_options(), config.device());
     app.run().await;
 
     Ok(())
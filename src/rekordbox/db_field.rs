@@ -80,6 +80,22 @@ impl DBField {
         }
     }
 
+    /// Decode the UTF-16 value of a string field.
+    pub fn as_string(&self) -> Option<String> {
+        if self.kind != DBFieldType::String {
+            return None;
+        }
+
+        let units = self.value
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect::<Vec<u16>>();
+
+        String::from_utf16(&units)
+            .ok()
+            .map(|value| value.trim_end_matches('\u{0}').to_string())
+    }
+
     pub fn as_bytes(&self) -> Bytes {
         let mut buffer = BytesMut::new();
 
@@ -196,6 +212,13 @@ mod test {
         ], field.as_bytes());
     }
 
+    #[test]
+    fn string_field_as_string() {
+        assert_eq!(Some("Loopmasters".to_string()), DBField::from("Loopmasters").as_string());
+        assert_eq!(Some("".to_string()), DBField::from("").as_string());
+        assert_eq!(None, DBField::from(1u32).as_string());
+    }
+
     #[test]
     fn empty_string_argument() {
         let field = DBField::from("");
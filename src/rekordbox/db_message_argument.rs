@@ -1,8 +1,12 @@
 use bytes::{Bytes, BytesMut};
 use nom::IResult;
 use nom::bytes::complete::{take};
+use nom::error::ErrorKind;
 use nom::number::complete::{be_u32, be_u16};
 use super::db_field::{DBField, DBFieldType};
+use crate::utils::parse_error;
+use std::convert::TryFrom;
+use std::fmt;
 use std::ops::Index;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -14,17 +18,40 @@ enum ArgumentType {
     Binary,
 }
 
-impl ArgumentType {
-    fn new(value: u8) -> ArgumentType {
-        match value {
+/// Why the arguments of a message can't be read.
+#[derive(Debug, PartialEq)]
+pub enum ArgumentError {
+    UnknownType(u8),
+    TooManyArguments(u8),
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgumentError::UnknownType(value) => write!(f, "Unknown argument type {:#04x}", value),
+            ArgumentError::TooManyArguments(count) => write!(f, "{} arguments, at most 12 are supported", count),
+        }
+    }
+}
+
+impl std::error::Error for ArgumentError {}
+
+impl TryFrom<u8> for ArgumentType {
+    type Error = ArgumentError;
+
+    fn try_from(value: u8) -> Result<ArgumentType, ArgumentError> {
+        Ok(match value {
             0x04 => ArgumentType::U8,
             0x05 => ArgumentType::U16,
             0x06 => ArgumentType::U32,
             0x02 => ArgumentType::String,
             0x03 => ArgumentType::Binary,
-            _ => panic!("Non-supported argument type."),
-        }
+            _ => return Err(ArgumentError::UnknownType(value)),
+        })
     }
+}
+
+impl ArgumentType {
 
     fn value(&self) -> u8 {
         match *self {
@@ -93,17 +120,37 @@ impl ArgumentCollection {
         self.0.len()
     }
 
+    pub fn get(&self, index: usize) -> Option<&DBField> {
+        self.0.get(index)
+    }
+
+    /// Argument types the header lists, checked before any argument is read.
+    fn argument_types(argument_count: u8, types: &[u8]) -> Result<Vec<ArgumentType>, ArgumentError> {
+        if argument_count as usize > types.len() {
+            return Err(ArgumentError::TooManyArguments(argument_count));
+        }
+
+        types[..argument_count as usize].iter()
+            .map(|value| ArgumentType::try_from(*value))
+            .collect()
+    }
+
     pub fn decode(input: &[u8]) -> IResult<&[u8], ArgumentCollection> {
         let (input, argument_count) = take(2u8)(input)?;
         let (input, _) = take(5u8)(input)?;
         let (mut input, argument_types) = take(12u8)(input)?;
 
-        let items = (0x00 .. argument_count[1]).into_iter().map(|counter| {
-            let argument_type = ArgumentType::new(argument_types[counter as usize]);
-            let args = Argument::decode(argument_type, &input).unwrap();
-            input = args.0;
-            args.1
-        }).collect::<Vec<DBField>>();
+        let argument_types = match Self::argument_types(argument_count[1], argument_types) {
+            Ok(argument_types) => argument_types,
+            Err(_err) => return Err(parse_error(input, ErrorKind::Verify)),
+        };
+
+        let mut items = Vec::with_capacity(argument_types.len());
+        for argument_type in argument_types {
+            let (rest, item) = Argument::decode(argument_type, input)?;
+            input = rest;
+            items.push(item);
+        }
 
         Ok((&[][..], ArgumentCollection(items)))
     }
@@ -144,7 +191,12 @@ impl Decode for Argument {
             ArgumentType::String => {
                 let (input, _) = take(1u8)(input)?;
                 let (input, variable_size) = be_u32(input)?;
-                let (input, data) = take((variable_size - 1) * 2)(input)?;
+                // The size counts the terminating NUL, which is read separately.
+                let units = match variable_size.checked_sub(1) {
+                    Some(units) => units,
+                    None => return Err(parse_error(input, ErrorKind::LengthValue)),
+                };
+                let (input, data) = take(units * 2)(input)?;
                 let (input, _) = be_u16(input)?;
 
                 Ok((input, DBField::new(DBFieldType::String, data)))
@@ -169,6 +221,11 @@ impl Decode for Argument {
                     return Ok((input, DBField::new(DBFieldType::Binary, &[])));
                 }
 
+                // Players send the type tag like for any other field.
+                let input = match input.first() {
+                    Some(tag) if *tag == DBFieldType::Binary.value() => &input[1..],
+                    _ => input,
+                };
                 let (input, variable_size) = be_u32(input)?;
                 let (input, data) = take(variable_size)(input)?;
                 Ok((input, DBField::new(DBFieldType::Binary, data)))
@@ -229,6 +286,15 @@ mod test {
         0x00, 0x00,
     ];
 
+    #[test]
+    fn test_decoding_unknown_argument_types_fails() {
+        assert_eq!(Err(ArgumentError::UnknownType(0x07)), ArgumentType::try_from(0x07));
+
+        let mut message = PARTIAL_RAW_MESSAGE.to_vec();
+        message[8] = 0x07;
+        assert!(ArgumentCollection::decode(&message).is_err());
+    }
+
     #[test]
     fn test_decoding_string_argument() {
         assert_eq!(
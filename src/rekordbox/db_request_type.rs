@@ -3,10 +3,16 @@ use bytes::Bytes;
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum DBRequestType {
     AlbumByArtistRequest,
+    AlbumByGenreArtistRequest,
     AlbumRequest,
+    ArtistByGenreRequest,
     ArtistRequest,
+    ArtworkRequest,
+    BeatGridRequest,
+    CueListRequest,
     GenreRequest,
     HistoryRequest,
+    KeyNeighbourRequest,
     KeyRequest,
     MenuFooter,
     MenuHeader,
@@ -22,8 +28,14 @@ pub enum DBRequestType {
     SearchQueryRequest,
     Setup,
     Success,
+    TitleByAlbumRequest,
     TitleByArtistAlbumRequest,
+    TitleByGenreArtistAlbumRequest,
+    TitleByHistoryRequest,
+    TitleByKeyDistanceRequest,
     TitleRequest,
+    Unavailable,
+    WaveformDetailRequest,
     Unknown(u16),
 }
 
@@ -31,7 +43,17 @@ impl DBRequestType {
     pub fn value(&self) -> Bytes {
         Bytes::from(match self {
             DBRequestType::AlbumByArtistRequest => "\x11\x02",
+            DBRequestType::AlbumByGenreArtistRequest => "\x12\x01",
+            DBRequestType::AlbumRequest => "\x10\x03",
+            DBRequestType::ArtistByGenreRequest => "\x11\x01",
             DBRequestType::ArtistRequest => "\x10\x02",
+            DBRequestType::ArtworkRequest => "\x20\x03",
+            DBRequestType::BeatGridRequest => "\x22\x04",
+            DBRequestType::CueListRequest => "\x21\x04",
+            DBRequestType::GenreRequest => "\x10\x01",
+            DBRequestType::HistoryRequest => "\x10\x12",
+            DBRequestType::KeyNeighbourRequest => "\x11\x14",
+            DBRequestType::KeyRequest => "\x10\x14",
             DBRequestType::LoadTrackRequest => "\x2b\x04",
             DBRequestType::MenuFooter => "\x42\x01",
             DBRequestType::MenuHeader => "\x40\x01",
@@ -39,16 +61,50 @@ impl DBRequestType {
             DBRequestType::LoadTrackSuccess => "\x4e\x02",
             DBRequestType::MetadataRequest => "\x20\x02",
             DBRequestType::MountInfoRequest => "\x21\x02",
+            DBRequestType::PlaylistRequest => "\x11\x05",
             DBRequestType::PreviewWaveformRequest => "\x20\x04",
             DBRequestType::RootMenuRequest => "\x10\x00",
             DBRequestType::RenderRequest => "\x30\x00",
+            DBRequestType::SearchQueryRequest => "\x13\x00",
             DBRequestType::Setup => "\x00\x00",
             DBRequestType::Success => "\x40\x00",
+            DBRequestType::TitleByAlbumRequest => "\x11\x03",
             DBRequestType::TitleByArtistAlbumRequest => "\x12\x02",
+            DBRequestType::TitleByGenreArtistAlbumRequest => "\x13\x01",
+            DBRequestType::TitleByHistoryRequest => "\x11\x12",
+            DBRequestType::TitleByKeyDistanceRequest => "\x12\x14",
+            DBRequestType::TitleRequest => "\x10\x04",
+            DBRequestType::Unavailable => "\x40\x03",
+            DBRequestType::WaveformDetailRequest => "\x29\x04",
             _ => "\x00\x00",
         })
     }
 
+    /// Requests that open a menu, their second argument is the sort order.
+    pub fn is_menu_request(&self) -> bool {
+        matches!(
+            self,
+            DBRequestType::AlbumByArtistRequest
+                | DBRequestType::AlbumByGenreArtistRequest
+                | DBRequestType::AlbumRequest
+                | DBRequestType::ArtistByGenreRequest
+                | DBRequestType::ArtistRequest
+                | DBRequestType::GenreRequest
+                | DBRequestType::HistoryRequest
+                | DBRequestType::KeyNeighbourRequest
+                | DBRequestType::KeyRequest
+                | DBRequestType::PlaylistRequest
+                | DBRequestType::RootMenuRequest
+                | DBRequestType::SearchQueryRequest
+                | DBRequestType::TitleByAlbumRequest
+                | DBRequestType::TitleByArtistAlbumRequest
+                | DBRequestType::TitleByGenreArtistAlbumRequest
+                | DBRequestType::TitleByHistoryRequest
+                | DBRequestType::TitleByKeyDistanceRequest
+                | DBRequestType::TitleRequest
+        )
+    }
+
     pub fn new(value: u16) -> DBRequestType {
         match value {
             0_u16    => DBRequestType::Setup,
@@ -59,17 +115,29 @@ impl DBRequestType {
             4100_u16 => DBRequestType::TitleRequest,
             4114_u16 => DBRequestType::HistoryRequest,
             4116_u16 => DBRequestType::KeyRequest,
+            4353_u16 => DBRequestType::ArtistByGenreRequest,
             4354_u16 => DBRequestType::AlbumByArtistRequest,
+            4355_u16 => DBRequestType::TitleByAlbumRequest,
             4357_u16 => DBRequestType::PlaylistRequest,
+            4370_u16 => DBRequestType::TitleByHistoryRequest,
+            4372_u16 => DBRequestType::KeyNeighbourRequest,
+            4609_u16 => DBRequestType::AlbumByGenreArtistRequest,
             4610_u16 => DBRequestType::TitleByArtistAlbumRequest,
+            4628_u16 => DBRequestType::TitleByKeyDistanceRequest,
             4864_u16 => DBRequestType::SearchQueryRequest,
+            4865_u16 => DBRequestType::TitleByGenreArtistAlbumRequest,
             8194_u16 => DBRequestType::MetadataRequest,
+            8195_u16 => DBRequestType::ArtworkRequest,
             8196_u16 => DBRequestType::PreviewWaveformRequest,
             8450_u16 => DBRequestType::MountInfoRequest,
+            8452_u16 => DBRequestType::CueListRequest,
+            8708_u16 => DBRequestType::BeatGridRequest,
+            10500_u16 => DBRequestType::WaveformDetailRequest,
             11012_u16 => DBRequestType::LoadTrackRequest,
             12288_u16 => DBRequestType::RenderRequest,
             16384_u16 => DBRequestType::Success,
             16385_u16 => DBRequestType::MenuHeader,
+            16387_u16 => DBRequestType::Unavailable,
             16641_u16 => DBRequestType::MenuItem,
             16897_u16 => DBRequestType::MenuFooter,
             _ => DBRequestType::Unknown(value)
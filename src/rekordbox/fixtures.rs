@@ -12,242 +12,6 @@ type DBMessageParse<'a> = DBMessage;
 /// (TypeRequest, TypeResponse, RenderRequest, RenderResponse)
 type IntegrationTestDialog = (Bytes, Bytes, Bytes, Bytes);
 
-pub fn title_by_artist_album_single_track_dialog() -> IntegrationTestDialog {
-    (
-        // QueryRequest
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x1a]),
-            DBRequestType::TitleByArtistAlbumRequest,
-            ArgumentCollection::new(vec![
-                DBField::from([0x02, 0x03, 0x04, 0x01]),
-                DBField::from([0x00, 0x00, 0x00, 0x00]),
-                DBField::from([0x00, 0x00, 0x00, 0x01]),
-                DBField::from([0x00, 0x00, 0x00, 0x00]),
-            ]),
-        )),
-
-        // QueryResponse
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x1a]),
-            DBRequestType::Success,
-            ArgumentCollection::new(vec![
-                DBField::from([0x00, 0x00, 0x12, 0x02]),
-                DBField::from(2u32),
-            ]),
-        )),
-
-        // RenderRequest
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x1a]),
-            DBRequestType::RenderRequest,
-            ArgumentCollection::new(vec![
-                DBField::from([0x02, 0x03, 0x04, 0x01]),
-                DBField::from(0u32),
-                DBField::from(1u32),
-                DBField::from(0u32),
-                DBField::from(2u32),
-                DBField::from(0u32),
-            ]),
-        )),
-
-        // RenderResponse
-        Bytes::from(ManyDBMessages::new(vec![
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x1a]),
-                DBRequestType::MenuHeader,
-                ArgumentCollection::new(vec![
-                    DBField::from(1u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x1a]),
-                DBRequestType::MenuItem,
-                ArgumentCollection::new(vec![
-                    DBField::from(0u32),
-                    DBField::from(5u32),
-                    DBField::from(26u32),
-                    DBField::from("Demo Track 1"),
-                    DBField::from(2u32),
-                    DBField::from(""),
-                    DBField::from(4u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x1a]),
-                DBRequestType::MenuFooter,
-                ArgumentCollection::new(vec![]),
-            )
-        ])),
-    )
-}
-
-pub fn title_by_artist_album_dialog() -> IntegrationTestDialog {
-    (
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x16]),
-            DBRequestType::TitleByArtistAlbumRequest,
-            ArgumentCollection::new(vec![
-                DBField::from([0x02, 0x02, 0x04, 0x01]), // player metadata
-                DBField::from(0u32), // sort_id
-                DBField::from(1u32), // item_id
-                DBField::from(0u32), // item_id
-            ]),
-        )),
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x16]),
-            DBRequestType::Success,
-            ArgumentCollection::new(vec![
-                DBField::from([0x00, 0x00, 0x12, 0x02]),
-                DBField::from(2u32),
-            ]),
-        )),
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x17]),
-            DBRequestType::RenderRequest,
-            ArgumentCollection::new(vec![
-                DBField::from([0x02, 0x02, 0x04, 0x01]),
-                DBField::from(0u32),
-                DBField::from(2u32),
-                DBField::from(0u32),
-                DBField::from(2u32),
-                DBField::from(0u32),
-            ]),
-        )),
-
-        Bytes::from(ManyDBMessages::new(vec![
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x17]),
-                DBRequestType::MenuHeader,
-                ArgumentCollection::new(vec![
-                    DBField::from(1u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x17]),
-                DBRequestType::MenuItem,
-                ArgumentCollection::new(vec![
-                    DBField::from(0u32),
-                    DBField::from(5u32),
-                    DBField::from(26u32),
-                    DBField::from("Demo Track 1"),
-                    DBField::from(2u32),
-                    DBField::from(""),
-                    DBField::from(metadata_type::TITLE),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x17]),
-                DBRequestType::MenuItem,
-                ArgumentCollection::new(vec![
-                    DBField::from(0u32),
-                    DBField::from(6u32),
-                    DBField::from(26u32),
-                    DBField::from("Demo Track 2"),
-                    DBField::from(2u32),
-                    DBField::from(""),
-                    DBField::from(metadata_type::TITLE),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x17]),
-                DBRequestType::MenuFooter,
-                ArgumentCollection::new(vec![]),
-            ),
-        ]))
-    )
-}
-
-pub fn album_by_artist_dialog() -> IntegrationTestDialog {
-    (
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x14]),
-            DBRequestType::AlbumByArtistRequest,
-            ArgumentCollection::new(vec![
-                // Player metadata
-                DBField::from([0x02, 0x02, 0x04, 0x01]),
-                // sort_id
-                DBField::from(0u32),
-                // menu_id
-                DBField::from(1u32),
-
-            ]),
-        )),
-
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x14]),
-            DBRequestType::Success,
-            ArgumentCollection::new(vec![
-                DBField::from([0x00, 0x00, 0x11, 0x02]),
-                DBField::from(1u32),
-            ]),
-        )),
-
-        Bytes::from(DBMessage::new(
-            DBField::from([0x05, 0x80, 0x00, 0x15]),
-            DBRequestType::RenderRequest,
-            ArgumentCollection::new(vec![
-                DBField::from([0x02, 0x02, 0x04, 0x01]),
-                DBField::from(0u32),
-                DBField::from(1u32),
-                DBField::from(0u32),
-                DBField::from(1u32),
-                DBField::from(0u32),
-            ]),
-        )),
-
-        Bytes::from(ManyDBMessages::new(vec![
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x15]),
-                DBRequestType::MenuHeader,
-                ArgumentCollection::new(vec![
-                    DBField::from(1u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x15]),
-                DBRequestType::MenuItem,
-                ArgumentCollection::new(vec![
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(16u32),
-                    DBField::from("Unknown"),
-                    DBField::from(2u32),
-                    DBField::from(""),
-                    DBField::from(metadata_type::ALBUM),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                    DBField::from(0u32),
-                ]),
-            ),
-            DBMessage::new(
-                DBField::from([0x05, 0x80, 0x00, 0x15]),
-                DBRequestType::MenuFooter,
-                ArgumentCollection::new(vec![]),
-            ),
-        ])),
-    )
-}
-
 pub fn setup_request_packet<'a>() -> DBMessageParseResult<'a> {
     DBMessage::parse(&[
         0x11, 0x87, 0x23, 0x49, 0xae, 0x11, 0xff, 0xff,
@@ -56,6 +56,7 @@ impl KeepAliveIpPackage {
 pub struct KeepAliveStatusPackage;
 impl KeepAliveStatusPackage {
     pub fn new(
+        player_number: u8,
         ip_addr: Ipv4Addr,
         mac_addr: MacAddr,
         unknown3: u16,
@@ -68,7 +69,7 @@ impl KeepAliveStatusPackage {
             unknown1: 1,
             device_type: DeviceType::Rekordbox,
             content: KeepAliveContentType::Status(Status {
-                player_number: 17,
+                player_number,
                 unknown2: 1,
                 ip_addr,
                 mac_addr,
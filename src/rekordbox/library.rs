@@ -1,32 +1,47 @@
 use bytes::{Bytes, BytesMut};
 use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
-use tokio::net::TcpListener;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
 use tokio_util::codec::{BytesCodec, Framed};
 
 use super::db_field::{DBField, DBFieldType};
 use super::db_message_argument::ArgumentCollection;
 use super::db_request_type::DBRequestType;
 use super::packets::{Arguments, DBMessage, ManyDBMessages};
-use crate::rekordbox::{Database, Record, ServerState};
+use crate::library::Analysis;
+use crate::rekordbox::{Color, Database, DeviceOptions, Record, ServerState, Track};
 use crate::utils::network::random_ipv4_socket_address;
 use futures::{SinkExt, StreamExt};
 
+pub mod analysis_cache;
 mod codec;
+mod cue_list;
 pub mod database;
-mod fixtures;
 mod helper;
+pub mod history;
 pub mod metadata_type;
 pub mod model;
 mod request;
-
-use fixtures::PREVIEW_WAVEFORM_RESPONSE;
+mod session;
+mod sort_order;
+pub mod store;
+pub mod watcher;
+
+use analysis_cache::AnalysisState;
+use database::{sorted_by_name, UNKNOWN_ALBUM_ID};
+use codec::{DbBytesCodec, DbFrame};
 use helper::*;
 pub use metadata_type::*;
-use request::{Controller, RequestHandler, RequestWrapper};
+use request::{unavailable_response, Controller, RequestError, RequestHandler, RequestWrapper};
+use session::{MenuLocation, MenuSessions};
+use sort_order::SortOrder;
 
 pub struct ClientState {
-    previous_request: Option<StatefulRequest>,
+    /// Player and menu the request being answered was sent from.
+    location: MenuLocation,
+    sessions: MenuSessions,
     state: Arc<Mutex<ServerState>>,
     database: Arc<Database>,
 }
@@ -34,14 +49,38 @@ pub struct ClientState {
 impl ClientState {
     pub fn new(state: Arc<Mutex<ServerState>>, database: Arc<Database>) -> Self {
         Self {
-            previous_request: None,
+            location: MenuLocation::default(),
+            sessions: MenuSessions::default(),
             state,
             database,
         }
     }
 
+    /// Share the menu sessions with the other connections of the server.
+    fn with_sessions(mut self, sessions: MenuSessions) -> Self {
+        self.sessions = sessions;
+        self
+    }
+
+    fn set_location(&mut self, location: MenuLocation) {
+        self.location = location;
+    }
+
     fn set_previous_request(&mut self, previous_request: StatefulRequest) {
-        self.previous_request = Some(previous_request);
+        self.sessions.update(self.location, |session| session.request = Some(previous_request));
+    }
+
+    /// The menu last asked for from where the current request was sent.
+    fn previous_request(&self) -> Option<StatefulRequest> {
+        self.sessions.get(self.location).request
+    }
+
+    fn set_sort_order(&mut self, sort_order: SortOrder) {
+        self.sessions.update(self.location, |session| session.sort_order = sort_order);
+    }
+
+    fn sort_order(&self) -> SortOrder {
+        self.sessions.get(self.location).sort_order
     }
 }
 
@@ -56,22 +95,26 @@ fn ok_request() -> Bytes {
 
 struct SetupController;
 impl Controller for SetupController {
-    fn to_response(&self, request: RequestWrapper, _context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
         let mut bytes: BytesMut = request.to_response();
+        let player_number = match context.state.lock() {
+            Ok(state) => state.device().player_number,
+            Err(_) => DeviceOptions::default().player_number,
+        };
 
         bytes.extend(ok_request());
         bytes.extend(Bytes::from(ArgumentCollection::new(vec![
             DBField::from([0x00, 0x00, 0x00, 0x00]),
-            DBField::from([0x00, 0x00, 0x00, 0x11]),
+            DBField::from(player_number as u32),
         ])));
 
-        Bytes::from(bytes)
+        Ok(Bytes::from(bytes))
     }
 }
 
 struct RootMenuController;
 impl Controller for RootMenuController {
-    fn to_response(&self, request: RequestWrapper, _context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, _context: &mut ClientState) -> Result<Bytes, RequestError> {
         let mut bytes: BytesMut = request.to_response();
 
         bytes.extend(ok_request());
@@ -80,32 +123,206 @@ impl Controller for RootMenuController {
             DBField::from([0x00, 0x00, 0x00, 0x08]),
         ])));
 
-        Bytes::from(bytes)
+        Ok(Bytes::from(bytes))
     }
 }
 
 struct AlbumByArtistController;
 impl Controller for AlbumByArtistController {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
         let request_type = &request.message.request_type.value();
-        let artist_id = dbfield_to_u32(&request.message.arguments[2]);
+        let artist_id = u32_argument(&request.message, 2)?;
 
         let mut bytes: BytesMut = request.to_response();
         bytes.extend(ok_request());
         bytes.extend(Bytes::from(ArgumentCollection::new(vec![
             DBField::from([0u8, 0u8, request_type[0], request_type[1]]),
-            DBField::from(number_of_tracks_by_artist(artist_id, &context.database)),
+            DBField::from(number_of_albums_by_artist(artist_id, &context.database)),
         ])));
 
         context.set_previous_request(StatefulRequest::AlbumByArtistRequest { artist_id });
 
-        Bytes::from(bytes)
+        Ok(Bytes::from(bytes))
+    }
+}
+
+struct AlbumController;
+impl Controller for AlbumController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let request_type = &request.message.request_type.value();
+        let mut bytes: BytesMut = request.to_response();
+
+        bytes.extend(ok_request());
+        bytes.extend(Bytes::from(ArgumentCollection::new(vec![
+            DBField::from([0u8, 0u8, request_type[0], request_type[1]]),
+            DBField::from(number_of_albums(&context.database)),
+        ])));
+
+        Ok(Bytes::from(bytes))
+    }
+}
+
+/// Success response announcing how many items the following render request returns.
+fn menu_count_response(request: RequestWrapper, count: u32) -> Bytes {
+    let request_type_value = request.message.request_type.value();
+
+    Bytes::from(DBMessage::new(
+        request.message.transaction_id,
+        DBRequestType::Success,
+        ArgumentCollection::new(vec![
+            DBField::from([0x00, 0x00, request_type_value[0], request_type_value[1]]),
+            DBField::from(count),
+        ]),
+    ))
+}
+
+struct GenreController;
+impl Controller for GenreController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let count = number_of_genres(&context.database);
+
+        context.set_previous_request(StatefulRequest::GenreRequest);
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct ArtistByGenreController;
+impl Controller for ArtistByGenreController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let genre_id = u32_argument(&request.message, 2)?;
+        let count = number_of_artists_by_genre(genre_id, &context.database);
+
+        context.set_previous_request(StatefulRequest::ArtistByGenreRequest { genre_id });
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct AlbumByGenreArtistController;
+impl Controller for AlbumByGenreArtistController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let genre_id = u32_argument(&request.message, 2)?;
+        let artist_id = u32_argument(&request.message, 3)?;
+        let count = number_of_albums_by_genre_artist(genre_id, artist_id, &context.database);
+
+        context.set_previous_request(StatefulRequest::AlbumByGenreArtistRequest { genre_id, artist_id });
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct TitleByGenreArtistAlbumController;
+impl Controller for TitleByGenreArtistAlbumController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let genre_id = u32_argument(&request.message, 2)?;
+        let artist_id = u32_argument(&request.message, 3)?;
+        let album_id = u32_argument(&request.message, 4)?;
+        let count = number_of_tracks_by_genre_artist_album(genre_id, artist_id, album_id, &context.database);
+
+        context.set_previous_request(StatefulRequest::TitleByGenreArtistAlbumRequest {
+            genre_id,
+            artist_id,
+            album_id,
+        });
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct KeyController;
+impl Controller for KeyController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let count = number_of_keys(&context.database);
+
+        context.set_previous_request(StatefulRequest::KeyRequest);
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+/// Distances offered after choosing a key, 0 being the key itself.
+const KEY_NEIGHBOUR_DISTANCES: u32 = 3;
+
+struct KeyNeighbourController;
+impl Controller for KeyNeighbourController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let key_id = u32_argument(&request.message, 2)?;
+
+        context.set_previous_request(StatefulRequest::KeyNeighbourRequest { key_id });
+
+        Ok(menu_count_response(request, KEY_NEIGHBOUR_DISTANCES))
+    }
+}
+
+struct TitleByKeyDistanceController;
+impl Controller for TitleByKeyDistanceController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let key_id = u32_argument(&request.message, 2)?;
+        let distance = u32_argument(&request.message, 3)?;
+        let count = number_of_tracks_by_key_distance(key_id, distance, &context.database);
+
+        context.set_previous_request(StatefulRequest::TitleByKeyDistanceRequest { key_id, distance });
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct PlaylistController;
+impl Controller for PlaylistController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let id = u32_argument(&request.message, 2)?;
+        let is_folder = u32_argument(&request.message, 3)? == 1;
+        let count = number_of_playlist_items(id, is_folder, &context.database);
+
+        context.set_previous_request(StatefulRequest::PlaylistRequest { id, is_folder });
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct HistoryController;
+impl Controller for HistoryController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let count = number_of_history_sessions(&context.database);
+
+        context.set_previous_request(StatefulRequest::HistoryRequest);
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct TitleByHistoryController;
+impl Controller for TitleByHistoryController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let history_id = u32_argument(&request.message, 2)?;
+        let count = number_of_tracks_by_history(history_id, &context.database);
+
+        context.set_previous_request(StatefulRequest::TitleByHistoryRequest { history_id });
+
+        Ok(menu_count_response(request, count))
+    }
+}
+
+struct SearchQueryController;
+impl Controller for SearchQueryController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        // The search text is the only string argument, preceded by its length.
+        let query = request.message.arguments
+            .iter()
+            .find_map(|argument| argument.as_string())
+            .unwrap_or_default();
+        let count = context.database.search(&query).len() as u32;
+
+        context.set_previous_request(StatefulRequest::SearchQueryRequest { query });
+
+        Ok(menu_count_response(request, count))
     }
 }
 
 struct ArtistController;
 impl Controller for ArtistController {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
         let request_type = &request.message.request_type.value();
         let mut bytes: BytesMut = request.to_response();
 
@@ -115,30 +332,177 @@ impl Controller for ArtistController {
             DBField::from(number_of_artists(&context.database)),
         ])));
 
-        Bytes::from(bytes)
+        Ok(Bytes::from(bytes))
+    }
+}
+
+/// Players don't ask for the waveforms or beat grid of a loaded track again,
+/// so requests for a track that isn't analysed yet wait this long for the
+/// analysis. Tracks taking longer show neither until they're loaded again.
+const ANALYSIS_TIMEOUT: Duration = Duration::from_secs(5);
+const ANALYSIS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Decode a track off the runtime, unless it's being analysed already.
+fn start_analysis(database: &Arc<Database>, track_id: u32) {
+    if database.queue_analysis(track_id) {
+        let database = database.clone();
+
+        tokio::task::spawn_blocking(move || {
+            database.analyse(track_id);
+            database.dequeue_analysis(track_id);
+        });
+    }
+}
+
+/// The track whose analysis `message` asks for.
+fn analysed_track(message: &DBMessage) -> Option<u32> {
+    let index = match message.request_type {
+        DBRequestType::PreviewWaveformRequest => 2,
+        DBRequestType::WaveformDetailRequest | DBRequestType::BeatGridRequest => 1,
+        _ => return None,
+    };
+
+    u32_argument(message, index).ok()
+}
+
+/// Wait up to `ANALYSIS_TIMEOUT` for the analysis `message` asks for.
+async fn wait_for_analysis(message: &DBMessage, database: &Arc<Database>) {
+    let track_id = match analysed_track(message) {
+        Some(track_id) => track_id,
+        None => return,
+    };
+
+    let is_pending = || database.analysis(track_id) == Some(AnalysisState::Pending);
+    if !is_pending() {
+        return;
+    }
+
+    start_analysis(database, track_id);
+    let analysed = async {
+        while is_pending() {
+            tokio::time::sleep(ANALYSIS_POLL_INTERVAL).await;
+        }
+    };
+
+    if timeout(ANALYSIS_TIMEOUT, analysed).await.is_err() {
+        eprintln!("Analysing track {} takes too long, answering without it", track_id);
+    }
+}
+
+/// The analysis of a track's audio, `None` when it can't be decoded. Tracks
+/// that weren't analysed yet are decoded off the runtime, the player is told
+/// the analysis is unavailable meanwhile, see `ANALYSIS_TIMEOUT`.
+fn analysis(context: &ClientState, track_id: u32) -> Result<Option<Analysis>, RequestError> {
+    match context.database.analysis(track_id) {
+        None => Err(RequestError::UnknownTrack(track_id)),
+        Some(AnalysisState::Analysed(analysis)) => Ok(Some(analysis)),
+        Some(AnalysisState::Failed) => Ok(None),
+        Some(AnalysisState::Pending) => {
+            start_analysis(&context.database, track_id);
+
+            Err(RequestError::AnalysisPending(track_id))
+        },
     }
 }
 
 struct PreviewWaveformController;
 impl Controller for PreviewWaveformController {
-    fn to_response(&self, request: RequestWrapper, _context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let track_id = u32_argument(&request.message, 2)?;
+        let waveform = analysis(context, track_id)?
+            .map(|analysis| analysis.preview)
+            .unwrap_or_default()
+            .to_bytes();
+
         let mut bytes: BytesMut = request.to_response();
         bytes.extend(Bytes::from(DBField::from([0x44, 0x02])));
         bytes.extend(Bytes::from(ArgumentCollection::new(vec![
             DBField::from([0x00, 0x00, 0x20, 0x04]),
             DBField::from(0u32),
-            DBField::from([0x00, 0x00, 0x03, 0x88]),
-            DBField::new(DBFieldType::Binary, &PREVIEW_WAVEFORM_RESPONSE),
+            DBField::from(waveform.len() as u32),
+            DBField::new(DBFieldType::Binary, &waveform),
         ])));
 
-        Bytes::from(bytes)
+        Ok(Bytes::from(bytes))
+    }
+}
+
+struct ArtworkController;
+impl Controller for ArtworkController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let artwork_id = u32_argument(&request.message, 1)?;
+        let artwork = context.database.artwork(artwork_id).unwrap_or_default();
+
+        let mut bytes: BytesMut = request.to_response();
+        bytes.extend(Bytes::from(DBField::from([0x40, 0x02])));
+        bytes.extend(Bytes::from(ArgumentCollection::new(vec![
+            DBField::from([0x00, 0x00, 0x20, 0x03]),
+            DBField::from(0u32),
+            DBField::from(artwork.len() as u32),
+            DBField::new(DBFieldType::Binary, &artwork),
+        ])));
+
+        Ok(Bytes::from(bytes))
+    }
+}
+
+struct BeatGridController;
+impl Controller for BeatGridController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let track_id = u32_argument(&request.message, 1)?;
+        let beat_grid = analysis(context, track_id)?
+            .map(|analysis| analysis.beat_grid)
+            .unwrap_or_default()
+            .to_bytes();
+
+        let mut bytes: BytesMut = request.to_response();
+        bytes.extend(Bytes::from(DBField::from([0x46, 0x02])));
+        bytes.extend(Bytes::from(ArgumentCollection::new(vec![
+            DBField::from([0x00, 0x00, 0x22, 0x04]),
+            DBField::from(0u32),
+            DBField::from(beat_grid.len() as u32),
+            DBField::new(DBFieldType::Binary, &beat_grid),
+        ])));
+
+        Ok(Bytes::from(bytes))
+    }
+}
+
+struct WaveformDetailController;
+impl Controller for WaveformDetailController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let track_id = u32_argument(&request.message, 1)?;
+        let waveform = analysis(context, track_id)?
+            .map(|analysis| analysis.detail)
+            .unwrap_or_default()
+            .to_bytes();
+
+        let mut bytes: BytesMut = request.to_response();
+        bytes.extend(Bytes::from(DBField::from([0x4a, 0x02])));
+        bytes.extend(Bytes::from(ArgumentCollection::new(vec![
+            DBField::from([0x00, 0x00, 0x29, 0x04]),
+            DBField::from(0u32),
+            DBField::from(waveform.len() as u32),
+            DBField::new(DBFieldType::Binary, &waveform),
+        ])));
+
+        Ok(Bytes::from(bytes))
     }
 }
 
 struct TitleController;
 impl Controller for TitleController {
-    fn to_response(&self, request: RequestWrapper, _context: &mut ClientState) -> Bytes {
-        request.to_response().freeze()
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let request_type = &request.message.request_type.value();
+        let mut bytes: BytesMut = request.to_response();
+
+        bytes.extend(ok_request());
+        bytes.extend(Bytes::from(ArgumentCollection::new(vec![
+            DBField::from([0u8, 0u8, request_type[0], request_type[1]]),
+            DBField::from(number_of_tracks(&context.database)),
+        ])));
+
+        Ok(Bytes::from(bytes))
     }
 }
 
@@ -179,15 +543,32 @@ fn build_message_footer(transaction_id: &DBField) -> DBMessage {
     )
 }
 
+/// Wrap menu items in a header and footer, keeping only the window the
+/// player asked for so long lists don't overflow it.
+fn build_menu_page(request: &RequestWrapper, items: Vec<DBMessage>) -> ManyDBMessages {
+    let transaction_id = &request.message.transaction_id;
+    let mut response = ManyDBMessages::new(vec![build_message_header(transaction_id)]);
+
+    response.extend(paginate(items, request));
+    response.push(DBMessage::new(
+        transaction_id.clone(),
+        DBRequestType::MenuFooter,
+        ArgumentCollection::new(vec![]),
+    ));
+
+    response
+}
+
 struct RenderController;
 impl RenderController {
     fn render_root_menu(&self, request: RequestWrapper, _context: &ClientState) -> ManyDBMessages {
         let transaction_id = request.message.transaction_id.clone();
         let mut response = ManyDBMessages::new(vec![build_message_header(&transaction_id)]);
 
-        response.extend(
+        response.extend(paginate(
             vec![
                 // MenuName, MetadataType, MenuId
+                ("\u{fffa}GENRE\u{fffb}", metadata_type::ROOT_GENRE, 0x01),
                 ("\u{fffa}ARTIST\u{fffb}", metadata_type::ROOT_ARTIST, 0x02),
                 ("\u{fffa}ALBUM\u{fffb}", metadata_type::ROOT_ALBUM, 0x03),
                 ("\u{fffa}TRACK\u{fffb}", metadata_type::ROOT_TRACK, 0x04),
@@ -203,105 +584,265 @@ impl RenderController {
             .iter()
             .map(|item| build_message_item(&transaction_id, item.0, item.1, item.2))
             .collect(),
-        );
+            &request,
+        ));
         response.push(build_message_footer(&transaction_id));
 
         response
     }
 
     fn render_artist_page(&self, request: RequestWrapper, context: &ClientState) -> ManyDBMessages {
-        let transaction_id = request.message.transaction_id;
-        let mut response = ManyDBMessages::new(vec![build_message_header(&transaction_id)]);
+        self.render_records(request, context.database.artists(), metadata_type::ARTIST)
+    }
+
+    fn render_records<T: Record>(
+        &self,
+        request: RequestWrapper,
+        records: Vec<T>,
+        entry_type: MetadataType,
+    ) -> ManyDBMessages {
+        let transaction_id = &request.message.transaction_id;
+        let items = sorted_by_name(records)
+            .iter()
+            .map(|record| build_message_item(transaction_id, record.name(), entry_type, *record.id()))
+            .collect();
+
+        build_menu_page(&request, items)
+    }
+
+    /// Like `render_records`, for lists with a meaningful order of their own.
+    fn render_ordered_records<T: Record>(
+        &self,
+        request: RequestWrapper,
+        records: Vec<T>,
+        entry_type: MetadataType,
+    ) -> ManyDBMessages {
+        let transaction_id = &request.message.transaction_id;
+        let items = records
+            .iter()
+            .map(|record| build_message_item(transaction_id, record.name(), entry_type, *record.id()))
+            .collect();
+
+        build_menu_page(&request, items)
+    }
+
+    /// Tracks in the order chosen in the sort menu of the player.
+    fn render_tracks(&self, request: RequestWrapper, context: &ClientState, tracks: Vec<Track>) -> ManyDBMessages {
+        let tracks = context.sort_order().sort(tracks, &context.database);
+
+        self.render_ordered_records(request, tracks, metadata_type::TITLE)
+    }
+
+    fn render_genre_page(&self, request: RequestWrapper, context: &ClientState) -> ManyDBMessages {
+        self.render_records(request, context.database.genres(), metadata_type::GENRE)
+    }
+
+    fn render_artist_by_genre(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        genre_id: u32,
+    ) -> ManyDBMessages {
+        self.render_records(request, context.database.artist_by_genre(genre_id), metadata_type::ARTIST)
+    }
 
-        for artist in context.database.artists() {
-            response.push(build_message_item(
-                &transaction_id,
-                artist.name().as_str(),
-                metadata_type::ARTIST,
-                *artist.id(),
+    fn render_album_by_genre_artist(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        genre_id: u32,
+        artist_id: u32,
+    ) -> ManyDBMessages {
+        let transaction_id = &request.message.transaction_id;
+        let mut items: Vec<DBMessage> = sorted_by_name(context.database.album_by_genre_artist(genre_id, artist_id))
+            .iter()
+            .map(|album| build_message_item(transaction_id, album.name(), metadata_type::ALBUM, *album.id()))
+            .collect();
+
+        if has_unknown_album_by_genre_artist(genre_id, artist_id, &context.database) {
+            items.push(build_message_item(
+                transaction_id,
+                "Unknown",
+                metadata_type::ALBUM,
+                UNKNOWN_ALBUM_ID,
             ));
         }
 
-        response.push(DBMessage::new(
-            transaction_id,
-            DBRequestType::MenuFooter,
-            ArgumentCollection::new(vec![]),
-        ));
+        build_menu_page(&request, items)
+    }
 
-        response
+    fn render_title_by_genre_artist_album(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        genre_id: u32,
+        artist_id: u32,
+        album_id: u32,
+    ) -> ManyDBMessages {
+        let tracks = context.database.title_by_genre_artist_album(genre_id, artist_id, album_id);
+
+        self.render_tracks(request, context, tracks)
     }
 
-    fn render_title_page(&self, request: RequestWrapper, _context: &ClientState) -> ManyDBMessages {
-        let transaction_id = request.message.transaction_id;
+    fn render_history_page(&self, request: RequestWrapper, context: &ClientState) -> ManyDBMessages {
+        // The latest set is the one most likely looked for.
+        let mut sessions = context.database.history_sessions();
+        sessions.reverse();
 
-        let mut response = ManyDBMessages::new(vec![build_message_header(&transaction_id)]);
-        response.push(build_message_item(
-            &transaction_id,
-            "Loopmasters",
-            metadata_type::TITLE,
-            0x05,
-        ));
-        response.push(DBMessage::new(
-            transaction_id,
-            DBRequestType::MenuFooter,
-            ArgumentCollection::new(vec![]),
-        ));
+        self.render_ordered_records(request, sessions, metadata_type::HISTORY_PLAYLIST)
+    }
 
-        response
+    fn render_title_by_history(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        history_id: u32,
+    ) -> ManyDBMessages {
+        // Played order.
+        self.render_tracks(request, context, context.database.title_by_history(history_id))
     }
 
-    fn render_album_by_artist(
+    fn render_key_page(&self, request: RequestWrapper, context: &ClientState) -> ManyDBMessages {
+        // Ordered around the Camelot wheel, 1A, 1B, 2A, ...
+        let mut keys = context.database.keys();
+        keys.sort_by_key(|key| (key.key().camelot_number(), !key.key().is_minor()));
+
+        self.render_ordered_records(request, keys, metadata_type::KEY)
+    }
+
+    fn render_key_neighbours(
         &self,
         request: RequestWrapper,
-        _context: &ClientState,
-        _artist_id: u32,
+        context: &ClientState,
+        key_id: u32,
     ) -> ManyDBMessages {
-        let transaction_id = request.message.transaction_id;
-        let mut response = ManyDBMessages::new(vec![build_message_header(&transaction_id)]);
+        let transaction_id = &request.message.transaction_id;
+        let name = context.database.get_key(key_id)
+            .map(|key| key.name().clone())
+            .unwrap_or_default();
+
+        let items = (0..KEY_NEIGHBOUR_DISTANCES)
+            .map(|distance| {
+                let label = match distance {
+                    0 => name.clone(),
+                    _ => format!("{} \u{b1}{}", name, distance),
+                };
+
+                DBMessage::new(
+                    transaction_id.clone(),
+                    DBRequestType::MenuItem,
+                    Arguments {
+                        entry_id1: key_id,
+                        entry_id2: distance,
+                        value1: &label,
+                        _type: metadata_type::KEY,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
 
-        response.push(build_message_item(
-            &transaction_id,
-            "Unknown",
-            metadata_type::ALBUM,
-            0x00,
-        ));
+        build_menu_page(&request, items)
+    }
 
-        response.push(DBMessage::new(
-            transaction_id,
-            DBRequestType::MenuFooter,
-            ArgumentCollection::new(vec![]),
-        ));
+    fn render_title_by_key_distance(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        key_id: u32,
+        distance: u32,
+    ) -> ManyDBMessages {
+        self.render_tracks(request, context, context.database.title_by_key_distance(key_id, distance))
+    }
 
-        response
+    fn render_playlist(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        id: u32,
+        is_folder: bool,
+    ) -> ManyDBMessages {
+        if !is_folder {
+            // Playlists keep their own order.
+            return self.render_tracks(request, context, context.database.title_by_playlist(id));
+        }
+
+        // Folders are listed before playlists.
+        let mut playlists = sorted_by_name(context.database.playlists_in_folder(id));
+        playlists.sort_by_key(|playlist| !playlist.is_folder);
+
+        let transaction_id = &request.message.transaction_id;
+        let items = playlists
+            .iter()
+            .map(|playlist| build_message_item(
+                transaction_id,
+                playlist.name(),
+                if playlist.is_folder { metadata_type::FOLDER } else { metadata_type::PLAYLIST },
+                *playlist.id(),
+            ))
+            .collect();
+
+        build_menu_page(&request, items)
     }
 
-    fn render_title_by_artist_album(
+    fn render_search_results(
         &self,
         request: RequestWrapper,
         context: &ClientState,
-        artist_id: u32,
+        query: &str,
     ) -> ManyDBMessages {
-        let transaction_id = request.message.transaction_id;
-        let tracks = context.database.title_by_artist(artist_id);
+        self.render_tracks(request, context, context.database.search(query))
+    }
 
-        let mut response = ManyDBMessages::new(vec![build_message_header(&transaction_id)]);
+    fn render_title_page(&self, request: RequestWrapper, context: &ClientState) -> ManyDBMessages {
+        self.render_tracks(request, context, context.database.tracks())
+    }
+
+    fn render_album_page(&self, request: RequestWrapper, context: &ClientState) -> ManyDBMessages {
+        self.render_records(request, context.database.albums(), metadata_type::ALBUM)
+    }
+
+    fn render_album_by_artist(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        artist_id: u32,
+    ) -> ManyDBMessages {
+        let transaction_id = &request.message.transaction_id;
+        let mut items: Vec<DBMessage> = sorted_by_name(context.database.album_by_artist(artist_id))
+            .iter()
+            .map(|album| build_message_item(transaction_id, album.name(), metadata_type::ALBUM, *album.id()))
+            .collect();
 
-        for track in tracks {
-            response.push(build_message_item(
-                &transaction_id,
-                &track.name().clone(),
-                metadata_type::TITLE,
-                *track.id(),
+        if context.database.has_unknown_album(artist_id) {
+            items.push(build_message_item(
+                transaction_id,
+                "Unknown",
+                metadata_type::ALBUM,
+                UNKNOWN_ALBUM_ID,
             ));
         }
 
-        response.push(DBMessage::new(
-            transaction_id,
-            DBRequestType::MenuFooter,
-            ArgumentCollection::new(vec![]),
-        ));
+        build_menu_page(&request, items)
+    }
 
-        response
+    fn render_title_by_album(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        album_id: u32,
+    ) -> ManyDBMessages {
+        self.render_tracks(request, context, context.database.title_by_album(album_id))
+    }
+
+    fn render_title_by_artist_album(
+        &self,
+        request: RequestWrapper,
+        context: &ClientState,
+        artist_id: u32,
+        album_id: u32,
+    ) -> ManyDBMessages {
+        self.render_tracks(request, context, context.database.title_by_artist_album(artist_id, album_id))
     }
 
     fn render_metadata(
@@ -309,12 +850,15 @@ impl RenderController {
         request: RequestWrapper,
         context: &ClientState,
         track_id: u32,
-    ) -> ManyDBMessages {
+    ) -> Result<ManyDBMessages, RequestError> {
         let transaction_id = request.message.transaction_id;
-        let track = context.database.get_track(track_id).unwrap();
-        let artist = context.database.get_artist(track.artist_id).unwrap();
+        let track = context.database.get_track(track_id).ok_or(RequestError::UnknownTrack(track_id))?;
+        let artist = context.database.get_artist(track.artist_id)
+            .ok_or(RequestError::UnknownArtist(track.artist_id))?;
+        let album = context.database.get_album(track.album_id);
+        let album_name = album.as_ref().map(|album| album.name().as_str()).unwrap_or("");
 
-        ManyDBMessages::new(vec![
+        Ok(ManyDBMessages::new(vec![
             build_message_header(&transaction_id),
             DBMessage::new(
                 transaction_id.clone(),
@@ -322,6 +866,7 @@ impl RenderController {
                 Arguments {
                     entry_id1: 1,
                     entry_id2: 5,
+                    entry_id3: track.artwork_id,
                     entry_id4: 256,
                     value1: track.name(),
                     _type: metadata_type::TITLE,
@@ -344,6 +889,8 @@ impl RenderController {
                 DBRequestType::MenuItem,
                 Arguments {
                     entry_id1: 1,
+                    entry_id2: track.album_id,
+                    value1: album_name,
                     _type: metadata_type::ALBUM,
                     ..Default::default()
                 },
@@ -352,7 +899,7 @@ impl RenderController {
                 transaction_id.clone(),
                 DBRequestType::MenuItem,
                 Arguments {
-                    entry_id2: 195,
+                    entry_id2: track.duration.unwrap_or(0),
                     _type: metadata_type::DURATION,
                     ..Default::default()
                 },
@@ -371,7 +918,7 @@ impl RenderController {
                 DBRequestType::MenuItem,
                 Arguments {
                     entry_id2: 5,
-                    value1: "",
+                    value1: &track.comment,
                     _type: metadata_type::COMMENT,
                     ..Default::default()
                 },
@@ -381,6 +928,8 @@ impl RenderController {
                 DBRequestType::MenuItem,
                 Arguments {
                     entry_id1: 1,
+                    entry_id2: track.key_id,
+                    value1: &track.key,
                     _type: metadata_type::KEY,
                     ..Default::default()
                 },
@@ -389,6 +938,7 @@ impl RenderController {
                 transaction_id.clone(),
                 DBRequestType::MenuItem,
                 Arguments {
+                    entry_id2: track.rating.unwrap_or(0) as u32,
                     _type: metadata_type::RATING,
                     ..Default::default()
                 },
@@ -397,7 +947,7 @@ impl RenderController {
                 transaction_id.clone(),
                 DBRequestType::MenuItem,
                 Arguments {
-                    _type: metadata_type::COLOR_NONE,
+                    _type: Color::metadata_type(track.color),
                     ..Default::default()
                 },
             ),
@@ -405,16 +955,36 @@ impl RenderController {
                 transaction_id.clone(),
                 DBRequestType::MenuItem,
                 Arguments {
+                    entry_id2: track.genre_id,
+                    value1: &track.genre,
                     _type: metadata_type::GENRE,
                     ..Default::default()
                 },
             ),
+            DBMessage::new(
+                transaction_id.clone(),
+                DBRequestType::MenuItem,
+                Arguments {
+                    value1: &track.label,
+                    _type: metadata_type::LABEL,
+                    ..Default::default()
+                },
+            ),
+            DBMessage::new(
+                transaction_id.clone(),
+                DBRequestType::MenuItem,
+                Arguments {
+                    entry_id2: track.year.unwrap_or(0),
+                    _type: metadata_type::YEAR,
+                    ..Default::default()
+                },
+            ),
             DBMessage::new(
                 transaction_id,
                 DBRequestType::MenuFooter,
                 ArgumentCollection::new(vec![]),
             ),
-        ])
+        ]))
     }
 
     fn render_mount_info(
@@ -422,73 +992,69 @@ impl RenderController {
         request: RequestWrapper,
         context: &ClientState,
         track_id: u32,
-    ) -> ManyDBMessages {
+    ) -> Result<ManyDBMessages, RequestError> {
         let transaction_id = request.message.transaction_id;
 
+        let track = context.database.get_track(track_id).ok_or(RequestError::UnknownTrack(track_id))?;
         let mut resp = ManyDBMessages::new(vec![build_message_header(&transaction_id)]);
 
-        match context.database.get_track(track_id) {
-            Some(track) => {
-                resp.push(DBMessage::new(
-                    transaction_id.clone(),
-                    DBRequestType::MenuItem,
-                    Arguments {
-                        _type: metadata_type::TITLE,
-                        entry_id2: 1,
-                        ..Default::default()
-                    },
-                ));
+        resp.push(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::MenuItem,
+            Arguments {
+                _type: metadata_type::TITLE,
+                entry_id2: 1,
+                ..Default::default()
+            },
+        ));
 
-                resp.push(DBMessage::new(
-                    transaction_id.clone(),
-                    DBRequestType::MenuItem,
-                    Arguments {
-                        _type: metadata_type::DURATION,
-                        entry_id2: 195,
-                        ..Default::default()
-                    },
-                ));
-                resp.push(DBMessage::new(
-                    transaction_id.clone(),
-                    DBRequestType::MenuItem,
-                    Arguments {
-                        _type: metadata_type::BPM,
-                        entry_id2: track.bpm.unwrap_or(0),
-                        ..Default::default()
-                    },
-                ));
-                resp.push(DBMessage::new(
-                    transaction_id.clone(),
-                    DBRequestType::MenuItem,
-                    Arguments {
-                        _type: metadata_type::COMMENT,
-                        value1: "Tracks by www.loopmasters.com",
-                        ..Default::default()
-                    },
-                ));
-                resp.push(DBMessage::new(
-                    transaction_id.clone(),
-                    DBRequestType::MenuItem,
-                    Arguments {
-                        _type: metadata_type::MOUNT_PATH,
-                        entry_id1: track.size,
-                        entry_id2: 5,
-                        value1: track.path(),
-                        ..Default::default()
-                    },
-                ));
-                resp.push(DBMessage::new(
-                    transaction_id.clone(),
-                    DBRequestType::MenuItem,
-                    Arguments {
-                        _type: metadata_type::UNKNOWN1,
-                        entry_id2: 1,
-                        ..Default::default()
-                    },
-                ));
-            }
-            None => panic!("Should not happen"),
-        };
+        resp.push(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::MenuItem,
+            Arguments {
+                _type: metadata_type::DURATION,
+                entry_id2: track.duration.unwrap_or(0),
+                ..Default::default()
+            },
+        ));
+        resp.push(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::MenuItem,
+            Arguments {
+                _type: metadata_type::BPM,
+                entry_id2: track.bpm.unwrap_or(0),
+                ..Default::default()
+            },
+        ));
+        resp.push(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::MenuItem,
+            Arguments {
+                _type: metadata_type::COMMENT,
+                value1: &track.comment,
+                ..Default::default()
+            },
+        ));
+        resp.push(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::MenuItem,
+            Arguments {
+                _type: metadata_type::MOUNT_PATH,
+                entry_id1: track.size,
+                entry_id2: 5,
+                value1: &track.path(),
+                ..Default::default()
+            },
+        ));
+        resp.push(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::MenuItem,
+            Arguments {
+                _type: metadata_type::UNKNOWN1,
+                entry_id2: 1,
+                ..Default::default()
+            },
+        ));
 
         resp.push(DBMessage::new(
             transaction_id,
@@ -496,149 +1062,274 @@ impl RenderController {
             ArgumentCollection::new(vec![]),
         ));
 
-        resp
+        Ok(resp)
     }
 }
 
 struct QueryMountInfoController;
 impl Controller for QueryMountInfoController {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
         let request_type_value = request.message.request_type.value();
         let items_to_render: u32 = 6u32;
-        let track_id = dbfield_to_u32(&request.message.arguments[1]);
+        let track_id = u32_argument(&request.message, 1)?;
 
         context.set_previous_request(StatefulRequest::MountInfoRequest { track_id });
 
-        Bytes::from(DBMessage::new(
+        Ok(Bytes::from(DBMessage::new(
             request.message.transaction_id,
             DBRequestType::Success,
             ArgumentCollection::new(vec![
                 DBField::from([0u8, 0u8, request_type_value[0], request_type_value[1]]),
                 DBField::from(items_to_render),
             ]),
-        ))
+        )))
     }
 }
 
-fn dbfield_to_u32(input: &DBField) -> u32 {
-    if input.kind != DBFieldType::U32 {
-        panic!("Unsupported conversation");
+/// Slice out the items asked for by a render request, its arguments are
+/// DMST, offset, limit, 0, total and 0.
+fn paginate<T>(items: Vec<T>, request: &RequestWrapper) -> Vec<T> {
+    let arguments = &request.message.arguments;
+    let window = arguments.get(1).and_then(dbfield_to_u32).zip(arguments.get(2).and_then(dbfield_to_u32));
+
+    match window {
+        Some((offset, limit)) => items.into_iter().skip(offset as usize).take(limit as usize).collect(),
+        None => items,
     }
+}
 
-    let mut inner_value: [u8; 4] = [0u8; 4];
-    let mut index = 0;
-    for val in input.value[..=3].iter() {
-        inner_value[index] = *val;
-        index += 1;
+fn dbfield_to_u32(input: &DBField) -> Option<u32> {
+    if input.kind != DBFieldType::U32 || input.value.len() != 4 {
+        return None;
     }
-    u32::from_be_bytes(inner_value)
+
+    Some(u32::from_be_bytes([input.value[0], input.value[1], input.value[2], input.value[3]]))
+}
+
+/// The number argument at `index` of a request.
+fn u32_argument(message: &DBMessage, index: usize) -> Result<u32, RequestError> {
+    let argument = message.arguments.get(index).ok_or(RequestError::MissingArgument(index))?;
+
+    dbfield_to_u32(argument).ok_or(RequestError::InvalidArgument(index))
 }
 
 struct TitleByArtistAlbumController;
 impl Controller for TitleByArtistAlbumController {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes {
-        let artist_id = dbfield_to_u32(&request.message.arguments[2]);
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let artist_id = u32_argument(&request.message, 2)?;
+        let album_id = u32_argument(&request.message, 3)?;
         let request_type_value = request.message.request_type.value();
-        let number_of_tracks_by_artist = number_of_tracks_by_artist(artist_id, &context.database);
+        let number_of_tracks = number_of_tracks_by_artist_album(artist_id, album_id, &context.database);
 
-        context.set_previous_request(StatefulRequest::TitleByArtistAlbumRequest { artist_id });
+        context.set_previous_request(StatefulRequest::TitleByArtistAlbumRequest { artist_id, album_id });
 
-        Bytes::from(DBMessage::new(
+        Ok(Bytes::from(DBMessage::new(
             request.message.transaction_id,
             DBRequestType::Success,
             ArgumentCollection::new(vec![
                 DBField::from([0x00, 0x00, request_type_value[0], request_type_value[1]]),
-                DBField::from(number_of_tracks_by_artist),
+                DBField::from(number_of_tracks),
             ]),
-        ))
+        )))
+    }
+}
+
+struct TitleByAlbumController;
+impl Controller for TitleByAlbumController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let album_id = u32_argument(&request.message, 2)?;
+        let request_type_value = request.message.request_type.value();
+        let number_of_tracks = number_of_tracks_by_album(album_id, &context.database);
+
+        context.set_previous_request(StatefulRequest::TitleByAlbumRequest { album_id });
+
+        Ok(Bytes::from(DBMessage::new(
+            request.message.transaction_id,
+            DBRequestType::Success,
+            ArgumentCollection::new(vec![
+                DBField::from([0x00, 0x00, request_type_value[0], request_type_value[1]]),
+                DBField::from(number_of_tracks),
+            ]),
+        )))
     }
 }
 
+/// Number of items rendered by `RenderController::render_metadata`.
+const METADATA_ITEM_COUNT: u32 = 12;
+
 struct MetadataController;
 impl Controller for MetadataController {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
         let request_type_value = request.message.request_type.value();
-        let track_id = dbfield_to_u32(&request.message.arguments[1]);
+        let track_id = u32_argument(&request.message, 1)?;
 
         context.set_previous_request(StatefulRequest::MetadataRequest { track_id });
 
-        Bytes::from(DBMessage::new(
+        Ok(Bytes::from(DBMessage::new(
             request.message.transaction_id,
             DBRequestType::Success,
             ArgumentCollection::new(vec![
                 DBField::from([0x00, 0x00, request_type_value[0], request_type_value[1]]),
-                DBField::from(10u32),
+                DBField::from(METADATA_ITEM_COUNT),
             ]),
-        ))
+        )))
     }
 }
 
+/// Players ask for the extended cue list of a track when loading it.
 struct LoadTrackController;
 impl Controller for LoadTrackController {
-    fn to_response(&self, request: RequestWrapper, _context: &mut ClientState) -> Bytes {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
         let request_type_value = request.message.request_type.value();
+        let cues = context.database.cues(u32_argument(&request.message, 1)?);
+        let cue_list = cue_list::extended_cue_list(&cues);
 
-        Bytes::from(DBMessage::new(
+        Ok(Bytes::from(DBMessage::new(
             request.message.transaction_id,
             DBRequestType::LoadTrackSuccess,
             ArgumentCollection::new(vec![
                 DBField::from([0u8, 0u8, request_type_value[0], request_type_value[1]]),
                 DBField::from(1u32),
-                DBField::from(0u32),
-                DBField::new(DBFieldType::Binary, &[]),
-                DBField::from(0u32),
+                DBField::from(cue_list.len() as u32),
+                DBField::new(DBFieldType::Binary, &cue_list),
+                DBField::from(cues.len() as u32),
             ]),
-        ))
+        )))
+    }
+}
+
+struct CueListController;
+impl Controller for CueListController {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let cues = context.database.cues(u32_argument(&request.message, 1)?);
+        let cue_list = cue_list::cue_list(&cues);
+
+        let mut bytes: BytesMut = request.to_response();
+        bytes.extend(Bytes::from(DBField::from([0x47, 0x02])));
+        bytes.extend(Bytes::from(ArgumentCollection::new(vec![
+            DBField::from([0x00, 0x00, 0x21, 0x04]),
+            DBField::from(0u32),
+            DBField::from(cue_list.len() as u32),
+            DBField::new(DBFieldType::Binary, &cue_list),
+            DBField::from(cues.len() as u32),
+        ])));
+
+        Ok(Bytes::from(bytes))
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 enum StatefulRequest {
     RootMenuRequest,
     ArtistRequest,
+    AlbumRequest,
     TitleRequest,
+    GenreRequest,
+    KeyRequest,
+    HistoryRequest,
+    TitleByHistoryRequest { history_id: u32 },
+    PlaylistRequest { id: u32, is_folder: bool },
+    SearchQueryRequest { query: String },
+    KeyNeighbourRequest { key_id: u32 },
+    TitleByKeyDistanceRequest { key_id: u32, distance: u32 },
     AlbumByArtistRequest { artist_id: u32 },
-    TitleByArtistAlbumRequest { artist_id: u32 },
+    ArtistByGenreRequest { genre_id: u32 },
+    AlbumByGenreArtistRequest { genre_id: u32, artist_id: u32 },
+    TitleByGenreArtistAlbumRequest { genre_id: u32, artist_id: u32, album_id: u32 },
+    TitleByAlbumRequest { album_id: u32 },
+    TitleByArtistAlbumRequest { artist_id: u32, album_id: u32 },
     MetadataRequest { track_id: u32 },
     MountInfoRequest { track_id: u32 },
 }
 
 impl Controller for RenderController {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes {
-        Bytes::from(match context.previous_request {
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError> {
+        let response = match context.previous_request() {
             Some(StatefulRequest::RootMenuRequest) => self.render_root_menu(request, context),
             Some(StatefulRequest::ArtistRequest) => self.render_artist_page(request, context),
+            Some(StatefulRequest::AlbumRequest) => self.render_album_page(request, context),
+            Some(StatefulRequest::GenreRequest) => self.render_genre_page(request, context),
+            Some(StatefulRequest::KeyRequest) => self.render_key_page(request, context),
+            Some(StatefulRequest::HistoryRequest) => self.render_history_page(request, context),
+            Some(StatefulRequest::TitleByHistoryRequest { history_id }) => {
+                self.render_title_by_history(request, context, history_id)
+            }
+            Some(StatefulRequest::PlaylistRequest { id, is_folder }) => {
+                self.render_playlist(request, context, id, is_folder)
+            }
+            Some(StatefulRequest::SearchQueryRequest { query }) => {
+                self.render_search_results(request, context, &query)
+            }
+            Some(StatefulRequest::KeyNeighbourRequest { key_id }) => {
+                self.render_key_neighbours(request, context, key_id)
+            }
+            Some(StatefulRequest::TitleByKeyDistanceRequest { key_id, distance }) => {
+                self.render_title_by_key_distance(request, context, key_id, distance)
+            }
+            Some(StatefulRequest::ArtistByGenreRequest { genre_id }) => {
+                self.render_artist_by_genre(request, context, genre_id)
+            }
+            Some(StatefulRequest::AlbumByGenreArtistRequest { genre_id, artist_id }) => {
+                self.render_album_by_genre_artist(request, context, genre_id, artist_id)
+            }
+            Some(StatefulRequest::TitleByGenreArtistAlbumRequest { genre_id, artist_id, album_id }) => {
+                self.render_title_by_genre_artist_album(request, context, genre_id, artist_id, album_id)
+            }
             Some(StatefulRequest::TitleRequest) => self.render_title_page(request, context),
             Some(StatefulRequest::AlbumByArtistRequest { artist_id }) => {
                 self.render_album_by_artist(request, context, artist_id)
             }
-            Some(StatefulRequest::TitleByArtistAlbumRequest { artist_id }) => {
-                self.render_title_by_artist_album(request, context, artist_id)
+            Some(StatefulRequest::TitleByAlbumRequest { album_id }) => {
+                self.render_title_by_album(request, context, album_id)
+            }
+            Some(StatefulRequest::TitleByArtistAlbumRequest { artist_id, album_id }) => {
+                self.render_title_by_artist_album(request, context, artist_id, album_id)
             }
             Some(StatefulRequest::MetadataRequest { track_id }) => {
-                self.render_metadata(request, context, track_id)
+                self.render_metadata(request, context, track_id)?
             }
             Some(StatefulRequest::MountInfoRequest { track_id }) => {
-                self.render_mount_info(request, context, track_id)
+                self.render_mount_info(request, context, track_id)?
             }
-            _ => ManyDBMessages::new(vec![]),
-        })
+            None => return Err(RequestError::NothingToRender),
+        };
+
+        Ok(Bytes::from(response))
     }
 }
 
 fn get_controller(request_type: &DBRequestType) -> Option<Box<dyn Controller>> {
     match request_type {
         DBRequestType::AlbumByArtistRequest => Some(Box::new(AlbumByArtistController)),
+        DBRequestType::AlbumByGenreArtistRequest => Some(Box::new(AlbumByGenreArtistController)),
+        DBRequestType::AlbumRequest => Some(Box::new(AlbumController)),
+        DBRequestType::ArtistByGenreRequest => Some(Box::new(ArtistByGenreController)),
         DBRequestType::ArtistRequest => Some(Box::new(ArtistController)),
+        DBRequestType::ArtworkRequest => Some(Box::new(ArtworkController)),
+        DBRequestType::BeatGridRequest => Some(Box::new(BeatGridController)),
+        DBRequestType::CueListRequest => Some(Box::new(CueListController)),
+        DBRequestType::GenreRequest => Some(Box::new(GenreController)),
+        DBRequestType::HistoryRequest => Some(Box::new(HistoryController)),
+        DBRequestType::KeyNeighbourRequest => Some(Box::new(KeyNeighbourController)),
+        DBRequestType::KeyRequest => Some(Box::new(KeyController)),
         DBRequestType::LoadTrackRequest => Some(Box::new(LoadTrackController)),
         DBRequestType::MetadataRequest => Some(Box::new(MetadataController)),
         DBRequestType::MountInfoRequest => Some(Box::new(QueryMountInfoController)),
+        DBRequestType::PlaylistRequest => Some(Box::new(PlaylistController)),
         DBRequestType::PreviewWaveformRequest => Some(Box::new(PreviewWaveformController)),
         DBRequestType::RenderRequest => Some(Box::new(RenderController)),
         DBRequestType::RootMenuRequest => Some(Box::new(RootMenuController)),
+        DBRequestType::SearchQueryRequest => Some(Box::new(SearchQueryController)),
         DBRequestType::Setup => Some(Box::new(SetupController)),
+        DBRequestType::TitleByAlbumRequest => Some(Box::new(TitleByAlbumController)),
         DBRequestType::TitleByArtistAlbumRequest => Some(Box::new(TitleByArtistAlbumController)),
+        DBRequestType::TitleByGenreArtistAlbumRequest => {
+            Some(Box::new(TitleByGenreArtistAlbumController))
+        }
+        DBRequestType::TitleByHistoryRequest => Some(Box::new(TitleByHistoryController)),
+        DBRequestType::TitleByKeyDistanceRequest => Some(Box::new(TitleByKeyDistanceController)),
         DBRequestType::TitleRequest => Some(Box::new(TitleController)),
+        DBRequestType::WaveformDetailRequest => Some(Box::new(WaveformDetailController)),
         _ => None,
     }
 }
@@ -647,10 +1338,18 @@ fn get_controller(request_type: &DBRequestType) -> Option<Box<dyn Controller>> {
 ///
 /// Some Controllers will extract some data from the request that is required
 /// for executing a future client request.
-fn handle_sequence_requests(context: &mut ClientState, request_type: &DBRequestType) {
-    match request_type {
+fn handle_sequence_requests(context: &mut ClientState, message: &DBMessage) {
+    if message.request_type.is_menu_request() {
+        if let Some(sort_order) = message.arguments.get(1).and_then(dbfield_to_u32) {
+            context.set_sort_order(SortOrder::new(sort_order));
+        }
+    }
+
+    match &message.request_type {
         DBRequestType::AlbumByArtistRequest => {}
+        DBRequestType::TitleByAlbumRequest => {}
         DBRequestType::TitleByArtistAlbumRequest => {}
+        DBRequestType::AlbumRequest => context.set_previous_request(StatefulRequest::AlbumRequest),
         DBRequestType::ArtistRequest => {
             context.set_previous_request(StatefulRequest::ArtistRequest)
         }
@@ -664,67 +1363,98 @@ fn handle_sequence_requests(context: &mut ClientState, request_type: &DBRequestT
     };
 }
 
-fn process(bytes: Bytes, context: &mut ClientState, _peer: &SocketAddr) -> Bytes {
-    // TODO: Before implementing DbBytesCodec this must be migrated.
-    if bytes.len() == 5 {
-        return Bytes::from(bytes);
+fn process(message: DBMessage, context: &mut ClientState, _peer: &SocketAddr) -> Bytes {
+    if let Some(request_handler) = get_controller(&message.request_type) {
+        context.set_location(MenuLocation::of(&message));
+        handle_sequence_requests(context, &message);
+
+        return RequestHandler::new(request_handler, message, context).respond_to();
     }
 
-    match DBMessage::parse(&bytes) {
-        Ok((_unprocessed_bytes, message)) => {
-            if let Some(request_handler) = get_controller(&message.request_type) {
-                handle_sequence_requests(context, &message.request_type);
+    eprintln!("DBRequestType: {:?} has no controller implemented.", &message.request_type);
+
+    DBMessage::new(
+        message.transaction_id,
+        DBRequestType::Success,
+        ArgumentCollection::new(vec![]),
+    )
+    .into()
+}
 
-                return RequestHandler::new(request_handler, message, context).respond_to();
-            } else {
-                eprintln!(
-                    "DBRequestType: {:?} has no controller implemented.\nRaw bytes: {:?}",
-                    &message.request_type, bytes
-                );
+/// Connections a player hasn't sent a request over for this long are closed,
+/// players connect again when they browse.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+async fn handle_library_client(socket: TcpStream, address: SocketAddr, mut context: ClientState) {
+    let mut remote_client = Framed::new(socket, DbBytesCodec::new());
+
+    loop {
+        let result = match timeout(IDLE_TIMEOUT, remote_client.next()).await {
+            Ok(Some(result)) => result,
+            Ok(None) => break,
+            Err(_elapsed) => {
+                eprintln!("closing idle library connection of {}", address);
+                break;
+            },
+        };
 
-                return DBMessage::new(
-                    message.transaction_id,
-                    DBRequestType::Success,
-                    ArgumentCollection::new(vec![]),
-                )
-                .into();
-            }
+        let response = match result {
+            Ok(DbFrame::Greeting(greeting)) => greeting,
+            Ok(DbFrame::Message(message)) => {
+                wait_for_analysis(&message, &context.database).await;
+                process(message, &mut context, &address)
+            },
+            Ok(DbFrame::Malformed { transaction_id, request_type }) => {
+                eprintln!("Failed reading the arguments of {:?}", request_type);
+                unavailable_response(transaction_id, request_type)
+            },
+            Err(err) => {
+                // The stream can't be read any further once framing is lost.
+                eprintln!("library client handler got error; error = {}", err);
+                break;
+            },
+        };
+
+        if let Err(err) = remote_client.send(response).await {
+            eprintln!("failed sending library query response; error = {}", err);
+            break;
         }
-        Err(nom::Err::Error(bytes)) => eprintln!("Error: {:?}", bytes),
-        _ => eprintln!("Not covered: {:?}", bytes),
     }
-
-    Bytes::from("panic")
 }
 
-async fn spawn_library_client_handler(
-    mut listener: TcpListener,
-    state: &Arc<Mutex<ServerState>>,
-    database: &Arc<Database>,
+/// Accept the connections of every player on the one library listener, each
+/// served on its own task.
+async fn serve_library_clients(
+    listener: TcpListener,
+    state: Arc<Mutex<ServerState>>,
+    database: Arc<Database>,
 ) {
-    match listener.accept().await {
-        Ok((remote_client, address)) => {
-            let mut remote_client = Framed::new(remote_client, BytesCodec::new());
-            let mut context = ClientState::new(state.clone(), database.clone());
-
-            while let Some(result) = remote_client.next().await {
-                match result {
-                    Ok(data) => {
-                        match remote_client
-                            .send(process(data.freeze(), &mut context, &address))
-                            .await
-                        {
-                            Ok(_) => {}
-                            Err(err) => {
-                                eprintln!("failed sending library query response; error = {}", err)
-                            }
-                        }
-                    }
-                    Err(err) => eprintln!("library client handler got error; error = {}", err),
-                }
+    let sessions = MenuSessions::default();
+
+    loop {
+        match listener.accept().await {
+            Ok((socket, address)) => {
+                let context = ClientState::new(state.clone(), database.clone())
+                    .with_sessions(sessions.clone());
+
+                tokio::spawn(handle_library_client(socket, address, context));
             }
+            Err(err) => eprintln!("failed reading connection on socket; error = {}", err),
+        }
+    }
+}
+
+/// Tell a player which port the library is served on, for every query it sends.
+async fn answer_port_queries(socket: TcpStream, port: u16) {
+    let mut socket = Framed::new(socket, BytesCodec::new());
+
+    while let Ok(Some(Ok(_query))) = timeout(IDLE_TIMEOUT, socket.next()).await {
+        let message = Bytes::from(port.to_be_bytes().to_vec());
+
+        if let Err(err) = socket.send(message).await {
+            eprintln!("failed sending library server port to client; error = {}", err);
+            break;
         }
-        Err(err) => eprintln!("failed reading connection on socket; error = {}", err),
     }
 }
 
@@ -736,48 +1466,26 @@ impl DBLibraryServer {
         database: Arc<Database>,
     ) -> Result<(), std::io::Error> {
         let addr = address.parse::<SocketAddr>().unwrap();
-        let mut listener = TcpListener::bind(&addr).await?;
+
+        Self::serve(TcpListener::bind(&addr).await?, state, database).await
+    }
+
+    /// Answer port queries on `listener` with the port of a library server
+    /// shared by all players.
+    async fn serve(
+        listener: TcpListener,
+        state: Arc<Mutex<ServerState>>,
+        database: Arc<Database>,
+    ) -> Result<(), std::io::Error> {
+        let library = TcpListener::bind(&random_ipv4_socket_address()).await?;
+        let port = library.local_addr()?.port();
+
+        tokio::spawn(serve_library_clients(library, state, database));
 
         loop {
             match listener.accept().await {
                 Ok((socket, _address)) => {
-                    let state = state.clone();
-                    let database = database.clone();
-
-                    tokio::spawn(async move {
-                        let mut socket = Framed::new(socket, BytesCodec::new());
-
-                        while let Some(result) = socket.next().await {
-                            match result {
-                                Ok(_data) => {
-                                    let state = state.clone();
-                                    let database = database.clone();
-                                    let allocated_socket =
-                                        TcpListener::bind(&random_ipv4_socket_address())
-                                            .await
-                                            .unwrap();
-                                    let allocated_port =
-                                        allocated_socket.local_addr().unwrap().port();
-
-                                    tokio::spawn(async move {
-                                        spawn_library_client_handler(
-                                            allocated_socket,
-                                            &state,
-                                            &database,
-                                        )
-                                        .await;
-                                    });
-                                    let message =
-                                        Bytes::from(allocated_port.to_be_bytes().to_vec());
-                                    match socket.send(message).await {
-                                        Err(err) => eprintln!("failed sending library server port to client; error = {}", err),
-                                        _ => {},
-                                    }
-                                }
-                                Err(_err) => {}
-                            };
-                        }
-                    });
+                    tokio::spawn(answer_port_queries(socket, port));
                 }
                 Err(err) => eprintln!("error accepting socket: {}", err),
             }
@@ -799,6 +1507,7 @@ mod test {
     use crate::rekordbox::{Database, ServerState};
     use pretty_assertions::assert_eq;
     use std::net::{IpAddr, Ipv4Addr};
+    use tokio_util::codec::Decoder;
 
     fn context() -> ClientState {
         ClientState::new(
@@ -807,14 +1516,21 @@ mod test {
         )
     }
 
+    fn request(bytes: Bytes) -> DBMessage {
+        match DbBytesCodec::new().decode(&mut BytesMut::from(&bytes[..])) {
+            Ok(Some(DbFrame::Message(message))) => message,
+            other => panic!("Expected a single request, got {:?}", other),
+        }
+    }
+
     fn peer() -> SocketAddr {
         SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 1234)
     }
 
     pub struct TestController;
     impl Controller for TestController {
-        fn to_response(&self, _request: RequestWrapper, _context: &mut ClientState) -> Bytes {
-            Bytes::from("my-very-test-value")
+        fn to_response(&self, _request: RequestWrapper, _context: &mut ClientState) -> Result<Bytes, RequestError> {
+            Ok(Bytes::from("my-very-test-value"))
         }
     }
 
@@ -848,45 +1564,327 @@ mod test {
         );
     }
 
+    fn unavailable(transaction_id: u32, request_type: DBRequestType) -> Bytes {
+        unavailable_response(DBField::from(transaction_id), request_type)
+    }
+
     #[test]
-    fn test_album_by_artist_dialog() {
-        let dialog = fixtures::album_by_artist_dialog();
+    fn test_unknown_tracks_are_unavailable() {
+        let mut context = context();
+        let dmst = DBField::from([0x11, 0x02, 0x01, 0x01]);
+
+        let metadata = DBMessage::new(
+            DBField::from(1u32),
+            DBRequestType::MetadataRequest,
+            ArgumentCollection::new(vec![dmst.clone(), DBField::from(404u32)]),
+        );
+        process(metadata, &mut context, &peer());
+
+        let render = DBMessage::new(
+            DBField::from(2u32),
+            DBRequestType::RenderRequest,
+            ArgumentCollection::new(vec![dmst, DBField::from(0u32), DBField::from(12u32)]),
+        );
+        assert_eq!(unavailable(2, DBRequestType::RenderRequest), process(render, &mut context, &peer()));
+    }
+
+    fn menu_request(request_type: DBRequestType, player: u8, menu: u8) -> DBMessage {
+        DBMessage::new(
+            DBField::from(1u32),
+            request_type,
+            ArgumentCollection::new(vec![
+                DBField::from([player, menu, 0x01, 0x01]),
+                DBField::from(0u32),
+            ]),
+        )
+    }
+
+    #[test]
+    fn test_menu_state_is_kept_per_player_and_menu() {
+        let sessions = MenuSessions::default();
+        let mut first = context().with_sessions(sessions.clone());
+        let mut second = context().with_sessions(sessions);
+
+        process(menu_request(DBRequestType::ArtistRequest, 2, 1), &mut first, &peer());
+        process(menu_request(DBRequestType::TitleRequest, 3, 1), &mut second, &peer());
+        process(menu_request(DBRequestType::AlbumRequest, 2, 2), &mut second, &peer());
+
+        first.set_location(MenuLocation { player: 2, menu: 1 });
+        assert_eq!(Some(StatefulRequest::ArtistRequest), first.previous_request());
+        first.set_location(MenuLocation { player: 3, menu: 1 });
+        assert_eq!(Some(StatefulRequest::TitleRequest), first.previous_request());
+        first.set_location(MenuLocation { player: 2, menu: 2 });
+        assert_eq!(Some(StatefulRequest::AlbumRequest), first.previous_request());
+        first.set_location(MenuLocation { player: 4, menu: 1 });
+        assert_eq!(None, first.previous_request());
+    }
+
+    #[tokio::test]
+    async fn test_players_share_one_library_server() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let query_address = listener.local_addr().unwrap();
+        let ctx = context();
+        tokio::spawn(DBLibraryServer::serve(listener, ctx.state, ctx.database));
+
+        let mut ports = vec![];
+        for _player in 0..2 {
+            let mut query = TcpStream::connect(query_address).await.unwrap();
+            query.write_all(b"\x00\x00\x00\x0fRemoteDBServer\x00").await.unwrap();
+            ports.push(query.read_u16().await.unwrap());
+        }
+        assert_eq!(ports[0], ports[1]);
+
+        let library_address = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), ports[0]);
+        let mut first = TcpStream::connect(library_address).await.unwrap();
+        let mut second = TcpStream::connect(library_address).await.unwrap();
+
+        for (client, greeting) in [(&mut second, 2u8), (&mut first, 1u8)] {
+            let greeting = [0x11, 0x00, 0x00, 0x00, greeting];
+            let mut echo = [0u8; 5];
+
+            client.write_all(&greeting).await.unwrap();
+            client.read_exact(&mut echo).await.unwrap();
+            assert_eq!(greeting, echo);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_waveforms_are_unavailable_until_analysed() {
+        let database = database::TestLibrary::new().track("Artist", "Album", "Unanalysed").build();
+        let track_id = *database.tracks()[0].id();
+        let mut context = ClientState::new(Arc::new(Mutex::new(ServerState::new())), Arc::new(database));
+
+        let preview = || DBMessage::new(
+            DBField::from(1u32),
+            DBRequestType::PreviewWaveformRequest,
+            ArgumentCollection::new(vec![
+                DBField::from([0x02, 0x01, 0x01, 0x01]),
+                DBField::from(0u32),
+                DBField::from(track_id),
+            ]),
+        );
+
+        assert_eq!(unavailable(1, DBRequestType::PreviewWaveformRequest), process(preview(), &mut context, &peer()));
+
+        while context.database.analysis(track_id) == Some(AnalysisState::Pending) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // The file doesn't exist, so the waveform stays empty.
+        assert_ne!(unavailable(1, DBRequestType::PreviewWaveformRequest), process(preview(), &mut context, &peer()));
+    }
+
+    #[tokio::test]
+    async fn test_connections_wait_for_the_analysis() {
+        let database = database::TestLibrary::new().track("Artist", "Album", "Unanalysed").build();
+        let track_id = *database.tracks()[0].id();
+        let mut context = ClientState::new(Arc::new(Mutex::new(ServerState::new())), Arc::new(database));
+        let beat_grid = DBMessage::new(
+            DBField::from(1u32),
+            DBRequestType::BeatGridRequest,
+            ArgumentCollection::new(vec![DBField::from([0x02, 0x01, 0x01, 0x01]), DBField::from(track_id)]),
+        );
+
+        wait_for_analysis(&beat_grid, &context.database).await;
+
+        assert_eq!(Some(AnalysisState::Failed), context.database.analysis(track_id));
+        assert_ne!(unavailable(1, DBRequestType::BeatGridRequest), process(beat_grid, &mut context, &peer()));
+    }
+
+    #[test]
+    fn test_bad_arguments_are_unavailable() {
         let mut context = context();
-        let peer_addr = peer();
 
-        assert_eq!(dialog.1, process(dialog.0, &mut context, &peer_addr));
+        let missing = DBMessage::new(
+            DBField::from(1u32),
+            DBRequestType::BeatGridRequest,
+            ArgumentCollection::new(vec![DBField::from([0x11, 0x02, 0x01, 0x01])]),
+        );
+        assert_eq!(unavailable(1, DBRequestType::BeatGridRequest), process(missing, &mut context, &peer()));
+
+        let not_a_number = DBMessage::new(
+            DBField::from(2u32),
+            DBRequestType::AlbumByArtistRequest,
+            ArgumentCollection::new(vec![
+                DBField::from([0x11, 0x02, 0x01, 0x01]),
+                DBField::from(0u32),
+                DBField::from("Loopmasters"),
+            ]),
+        );
+        assert_eq!(unavailable(2, DBRequestType::AlbumByArtistRequest), process(not_a_number, &mut context, &peer()));
+    }
+
+    #[test]
+    fn test_rendering_without_a_menu_is_unavailable() {
+        let render = DBMessage::new(
+            DBField::from(1u32),
+            DBRequestType::RenderRequest,
+            ArgumentCollection::new(vec![DBField::from([0x11, 0x02, 0x01, 0x01])]),
+        );
+
+        assert_eq!(unavailable(1, DBRequestType::RenderRequest), process(render, &mut context(), &peer()));
+    }
+
+    #[test]
+    fn test_menu_page_renders_requested_window() {
+        let transaction_id = DBField::from(0x05000001u32);
+        // DMST, offset, limit, 0, total, 0
+        let request = RequestWrapper::new(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::RenderRequest,
+            ArgumentCollection::new(vec![
+                DBField::from([0x11, 0x01, 0x01, 0x01]),
+                DBField::from(2u32),
+                DBField::from(2u32),
+                DBField::from(0u32),
+                DBField::from(5u32),
+                DBField::from(0u32),
+            ]),
+        ));
+        let names = ["a", "b", "c", "d", "e"];
+        let items = names.iter()
+            .enumerate()
+            .map(|(id, name)| build_message_item(&transaction_id, name, metadata_type::TITLE, id as u32))
+            .collect();
+
+        let page = build_menu_page(&request, items).into_iter().collect::<Vec<DBMessage>>();
+
+        assert_eq!(4, page.len());
+        assert_eq!(build_message_header(&transaction_id), page[0]);
+        assert_eq!(build_message_item(&transaction_id, "c", metadata_type::TITLE, 2), page[1]);
+        assert_eq!(build_message_item(&transaction_id, "d", metadata_type::TITLE, 3), page[2]);
+        assert_eq!(DBRequestType::MenuFooter, page[3].request_type);
+    }
+
+    /// Loopmasters with a "Demo" album and a track without album, and
+    /// another artist with a single track album.
+    fn library_context() -> ClientState {
+        let database = database::TestLibrary::new()
+            .track("Loopmasters", "Demo", "Demo Track 2")
+            .track("Loopmasters", "Demo", "Demo Track 1")
+            .track("Loopmasters", "", "Loose Track")
+            .track("Other Artist", "Other", "Other Track")
+            .build();
+
+        ClientState::new(Arc::new(Mutex::new(ServerState::new())), Arc::new(database))
+    }
+
+    fn artist_id(context: &ClientState, name: &str) -> u32 {
+        *context.database.artists().iter().find(|artist| artist.name() == name).unwrap().id()
+    }
+
+    fn album_id(context: &ClientState, name: &str) -> u32 {
+        *context.database.albums().iter().find(|album| album.name() == name).unwrap().id()
+    }
+
+    fn browse_request(transaction_id: &DBField, request_type: DBRequestType, ids: &[u32]) -> DBMessage {
+        let mut arguments = vec![DBField::from([0x02, 0x02, 0x04, 0x01]), DBField::from(0u32)];
+        arguments.extend(ids.iter().map(|id| DBField::from(*id)));
+
+        DBMessage::new(transaction_id.clone(), request_type, ArgumentCollection::new(arguments))
+    }
+
+    fn count_response(transaction_id: &DBField, request_type: DBRequestType, count: u32) -> Bytes {
+        let request_type = request_type.value();
+
+        Bytes::from(DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::Success,
+            ArgumentCollection::new(vec![
+                DBField::from([0x00, 0x00, request_type[0], request_type[1]]),
+                DBField::from(count),
+            ]),
+        ))
+    }
+
+    fn render_request(transaction_id: &DBField, count: u32) -> DBMessage {
+        DBMessage::new(
+            transaction_id.clone(),
+            DBRequestType::RenderRequest,
+            ArgumentCollection::new(vec![
+                DBField::from([0x02, 0x02, 0x04, 0x01]),
+                DBField::from(0u32),
+                DBField::from(count),
+                DBField::from(0u32),
+                DBField::from(count),
+                DBField::from(0u32),
+            ]),
+        )
+    }
+
+    fn menu_page(transaction_id: &DBField, entry_type: MetadataType, items: &[(&str, u32)]) -> Bytes {
+        let mut page = vec![build_message_header(transaction_id)];
+        page.extend(items.iter().map(|(name, id)| build_message_item(transaction_id, name, entry_type, *id)));
+        page.push(DBMessage::new(transaction_id.clone(), DBRequestType::MenuFooter, ArgumentCollection::new(vec![])));
+
+        Bytes::from(ManyDBMessages::new(page))
+    }
+
+    #[test]
+    fn test_album_by_artist_dialog() {
+        let mut context = library_context();
+        let artist_id = artist_id(&context, "Loopmasters");
+        let album_id = album_id(&context, "Demo");
+        let (query, render) = (DBField::from(0x05800014u32), DBField::from(0x05800015u32));
+
+        assert_eq!(
+            count_response(&query, DBRequestType::AlbumByArtistRequest, 2),
+            process(browse_request(&query, DBRequestType::AlbumByArtistRequest, &[artist_id]), &mut context, &peer()),
+        );
         assert_eq!(
-            Some(StatefulRequest::AlbumByArtistRequest { artist_id: 0u32 }),
-            context.previous_request,
+            Some(StatefulRequest::AlbumByArtistRequest { artist_id }),
+            context.previous_request(),
+        );
+        assert_eq!(
+            menu_page(&render, metadata_type::ALBUM, &[("Demo", album_id), ("Unknown", UNKNOWN_ALBUM_ID)]),
+            process(render_request(&render, 2), &mut context, &peer()),
         );
-        assert_eq!(dialog.3, process(dialog.2, &mut context, &peer_addr));
     }
 
     #[test]
     fn test_title_by_artist_dialog() {
-        let dialog = fixtures::title_by_artist_album_dialog();
-        let mut context = context();
-        let peer_addr = peer();
+        let mut context = library_context();
+        let artist_id = artist_id(&context, "Loopmasters");
+        let album_id = album_id(&context, "Demo");
+        let track_id = |title: &str| *context.database.tracks().iter().find(|track| track.name() == title).unwrap().id();
+        let tracks = [("Demo Track 1", track_id("Demo Track 1")), ("Demo Track 2", track_id("Demo Track 2"))];
+        let (query, render) = (DBField::from(0x05800016u32), DBField::from(0x05800017u32));
 
-        assert_eq!(dialog.1, process(dialog.0, &mut context, &peer_addr));
         assert_eq!(
-            Some(StatefulRequest::TitleByArtistAlbumRequest { artist_id: 0u32 }),
-            context.previous_request,
+            count_response(&query, DBRequestType::TitleByArtistAlbumRequest, 2),
+            process(browse_request(&query, DBRequestType::TitleByArtistAlbumRequest, &[artist_id, album_id]), &mut context, &peer()),
+        );
+        assert_eq!(
+            Some(StatefulRequest::TitleByArtistAlbumRequest { artist_id, album_id }),
+            context.previous_request(),
+        );
+        assert_eq!(
+            menu_page(&render, metadata_type::TITLE, &tracks),
+            process(render_request(&render, 2), &mut context, &peer()),
         );
-        assert_eq!(dialog.3, process(dialog.2, &mut context, &peer_addr));
     }
 
     #[test]
     fn test_title_by_artist_dialog_single_track() {
-        let dialog = fixtures::title_by_artist_album_single_track_dialog();
-        let mut context = context();
-        let peer_addr = peer();
+        let mut context = library_context();
+        let artist_id = artist_id(&context, "Other Artist");
+        let album_id = album_id(&context, "Other");
+        let track_id = *context.database.tracks().iter().find(|track| track.name() == "Other Track").unwrap().id();
+        let transaction_id = DBField::from(0x0580001au32);
 
-        assert_eq!(dialog.1, process(dialog.0, &mut context, &peer_addr));
         assert_eq!(
-            Some(StatefulRequest::TitleByArtistAlbumRequest { artist_id: 0u32 }),
-            context.previous_request
+            count_response(&transaction_id, DBRequestType::TitleByArtistAlbumRequest, 1),
+            process(browse_request(&transaction_id, DBRequestType::TitleByArtistAlbumRequest, &[artist_id, album_id]), &mut context, &peer()),
+        );
+        assert_eq!(
+            Some(StatefulRequest::TitleByArtistAlbumRequest { artist_id, album_id }),
+            context.previous_request(),
+        );
+        assert_eq!(
+            menu_page(&transaction_id, metadata_type::TITLE, &[("Other Track", track_id)]),
+            process(render_request(&transaction_id, 1), &mut context, &peer()),
         );
-        assert_eq!(dialog.3, process(dialog.2, &mut context, &peer_addr));
     }
 }
@@ -0,0 +1,261 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::library::{analyse, Analysis};
+use crate::utils::data_folder;
+use super::database::{Record, Track};
+use super::store;
+
+/// Bumped whenever `Analysis` changes, tracks analysed by other versions are
+/// analysed again.
+const ANALYSIS_VERSION: u32 = 3;
+
+pub fn default_analysis_folder() -> Option<PathBuf> {
+    Some(data_folder()?.join("analysis"))
+}
+
+/// Analyses of this many tracks are kept in memory, enough for the tracks
+/// loaded on every player of a booth.
+const RECENT_ANALYSES: usize = 16;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedAnalysis {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    analysis: Analysis,
+}
+
+impl CachedAnalysis {
+    fn is_of(&self, track: &Track) -> bool {
+        self.path == track.path && self.modified == track.modified
+    }
+}
+
+/// How far the analysis of a track got.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisState {
+    /// Not analysed yet, or the file changed since.
+    Pending,
+    /// The audio couldn't be decoded.
+    Failed,
+    Analysed(Analysis),
+}
+
+/// Analyses of the tracks' audio, one file per track id below `folder`.
+/// Decoding a track takes a while, so tracks are only analysed the first time
+/// a player asks for them and again once their file changed. The analyses of
+/// the tracks asked for last are kept in memory as well.
+#[derive(Debug, Default)]
+pub struct AnalysisCache {
+    folder: Option<PathBuf>,
+    /// Most recently used first.
+    recent: Mutex<VecDeque<(u32, CachedAnalysis)>>,
+    /// Versions of tracks whose audio couldn't be decoded.
+    failed: Mutex<HashMap<u32, (PathBuf, Option<SystemTime>)>>,
+    /// Tracks being analysed, so they're only decoded once.
+    queued: Mutex<HashSet<u32>>,
+}
+
+impl AnalysisCache {
+    pub fn new(folder: Option<PathBuf>) -> Self {
+        Self {
+            folder,
+            ..Default::default()
+        }
+    }
+
+    /// Whether analyses outlive the process, computing them up front is pointless otherwise.
+    pub fn is_persistent(&self) -> bool {
+        self.folder.is_some()
+    }
+
+    fn file(&self, track: &Track) -> Option<PathBuf> {
+        self.folder.as_ref().map(|folder| folder.join(format!("{}.bin", track.id())))
+    }
+
+    fn load(&self, track: &Track) -> Option<CachedAnalysis> {
+        let cached = store::load_version::<CachedAnalysis>(&self.file(track)?, ANALYSIS_VERSION)?;
+
+        if cached.is_of(track) { Some(cached) } else { None }
+    }
+
+    fn recent(&self, track: &Track) -> Option<Analysis> {
+        let mut recent = self.recent.lock().ok()?;
+        let index = recent.iter().position(|(track_id, cached)| track_id == track.id() && cached.is_of(track))?;
+        let entry = recent.remove(index)?;
+        let analysis = entry.1.analysis.clone();
+        recent.push_front(entry);
+
+        Some(analysis)
+    }
+
+    fn remember(&self, track: &Track, cached: CachedAnalysis) {
+        if let Ok(mut recent) = self.recent.lock() {
+            recent.retain(|(track_id, _cached)| track_id != track.id());
+            recent.push_front((*track.id(), cached));
+            recent.truncate(RECENT_ANALYSES);
+        }
+    }
+
+    fn has_failed(&self, track: &Track) -> bool {
+        match self.failed.lock() {
+            Ok(failed) => failed.get(track.id()) == Some(&(track.path.clone(), track.modified)),
+            Err(_) => false,
+        }
+    }
+
+    /// The analysis of `track` as far as it's known, without decoding it.
+    pub fn state(&self, track: &Track) -> AnalysisState {
+        if let Some(analysis) = self.recent(track) {
+            return AnalysisState::Analysed(analysis);
+        }
+
+        if self.has_failed(track) {
+            return AnalysisState::Failed;
+        }
+
+        match self.load(track) {
+            Some(cached) => {
+                let analysis = cached.analysis.clone();
+                self.remember(track, cached);
+                AnalysisState::Analysed(analysis)
+            },
+            None => AnalysisState::Pending,
+        }
+    }
+
+    /// Claim `track_id` for analysis, false when it's being analysed already.
+    pub fn queue(&self, track_id: u32) -> bool {
+        match self.queued.lock() {
+            Ok(mut queued) => queued.insert(track_id),
+            Err(_) => false,
+        }
+    }
+
+    /// Release the claim on `track_id` taken with `queue`.
+    pub fn dequeue(&self, track_id: u32) {
+        if let Ok(mut queued) = self.queued.lock() {
+            queued.remove(&track_id);
+        }
+    }
+
+    /// The analysis of `track`, decoding it when it wasn't analysed before.
+    pub fn analysis(&self, track: &Track) -> Option<Analysis> {
+        match self.state(track) {
+            AnalysisState::Analysed(analysis) => Some(analysis),
+            AnalysisState::Failed => None,
+            AnalysisState::Pending => {
+                let cached = self.analyse(track)?;
+                let analysis = cached.analysis.clone();
+                self.remember(track, cached);
+                Some(analysis)
+            },
+        }
+    }
+
+    /// Make sure `track` is analysed on disk, without keeping the analysis in
+    /// memory.
+    pub fn prepare(&self, track: &Track) {
+        if !self.has_failed(track) && self.load(track).is_none() {
+            self.analyse(track);
+        }
+    }
+
+    fn analyse(&self, track: &Track) -> Option<CachedAnalysis> {
+        let analysis = match analyse(&track.path) {
+            Some(analysis) => analysis,
+            None => {
+                if let Ok(mut failed) = self.failed.lock() {
+                    failed.insert(*track.id(), (track.path.clone(), track.modified));
+                }
+                return None;
+            },
+        };
+
+        let cached = CachedAnalysis {
+            path: track.path.clone(),
+            modified: track.modified,
+            analysis,
+        };
+
+        if let Some(file) = self.file(track) {
+            if let Err(err) = store::save_version(&file, ANALYSIS_VERSION, &cached) {
+                eprintln!("Failed caching analysis of {}; error = {}", track.path.display(), err);
+            }
+        }
+
+        Some(cached)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::database::TestLibrary;
+
+    #[test]
+    fn it_serves_cached_analyses_until_the_file_changes() {
+        let folder = std::env::temp_dir().join(format!("termdj-analysis-{}", std::process::id()));
+        let database = TestLibrary::new().track("Artist", "Album", "Cached").build();
+        let mut track = database.tracks().remove(0);
+
+        let analysis = Analysis::default();
+        let file = folder.join(format!("{}.bin", track.id()));
+        store::save_version(&file, ANALYSIS_VERSION, &CachedAnalysis {
+            path: track.path.clone(),
+            modified: track.modified,
+            analysis: analysis.clone(),
+        }).unwrap();
+
+        let cache = AnalysisCache::new(Some(folder.clone()));
+        let cached = cache.analysis(&track);
+        track.modified = Some(SystemTime::now());
+        let changed = cache.analysis(&track);
+        std::fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(cached, Some(analysis));
+        // The file doesn't exist, so it can't be analysed again.
+        assert_eq!(changed, None);
+    }
+
+    #[test]
+    fn it_remembers_tracks_that_cannot_be_decoded() {
+        let database = TestLibrary::new().track("Artist", "Album", "Broken").build();
+        let track = database.tracks().remove(0);
+        let cache = AnalysisCache::default();
+
+        assert_eq!(AnalysisState::Pending, cache.state(&track));
+        assert!(cache.queue(*track.id()));
+        assert!(!cache.queue(*track.id()));
+
+        assert_eq!(None, cache.analysis(&track));
+        assert_eq!(AnalysisState::Failed, cache.state(&track));
+        cache.dequeue(*track.id());
+        assert!(cache.queue(*track.id()));
+    }
+
+    #[test]
+    fn it_keeps_only_the_recent_analyses_in_memory() {
+        let database = (0..=RECENT_ANALYSES)
+            .fold(TestLibrary::new(), |library, title| library.track("Artist", "Album", &title.to_string()))
+            .build();
+        let tracks = database.tracks();
+        let cache = AnalysisCache::default();
+        let analysis = Analysis::default();
+
+        for track in &tracks {
+            cache.remember(track, CachedAnalysis {
+                path: track.path.clone(),
+                modified: track.modified,
+                analysis: analysis.clone(),
+            });
+        }
+
+        assert_eq!(RECENT_ANALYSES, cache.recent.lock().unwrap().len());
+        assert_eq!(AnalysisState::Pending, cache.state(&tracks[0]));
+        assert_eq!(AnalysisState::Analysed(analysis), cache.state(&tracks[RECENT_ANALYSES]));
+    }
+}
@@ -1,39 +1,202 @@
+use crate::rekordbox::db_field::{DBField, DBFieldType};
+use crate::rekordbox::db_request_type::DBRequestType;
 use crate::rekordbox::packets::DBMessage;
 use bytes::Bytes;
-use std::convert::TryFrom;
 use std::io::{Error, ErrorKind};
 use tokio_util::codec::{Decoder, Encoder};
 
 use bytes::BytesMut;
 
+/// Clients open a connection with a single number field, which is echoed back.
+const GREETING_LENGTH: usize = 5;
+
+/// Magic, transaction id, request type, argument count and the binary field
+/// holding the argument types, the arguments follow.
+const HEADER_LENGTH: usize = 32;
+const TRANSACTION_ID_OFFSET: usize = 5;
+const REQUEST_TYPE_OFFSET: usize = 10;
+const ARGUMENT_COUNT_OFFSET: usize = 14;
+const ARGUMENT_TYPES_OFFSET: usize = 20;
+const MAX_ARGUMENTS: usize = 12;
+
+/// Requests are a few hundred bytes at most, anything larger isn't a request.
+const MAX_MESSAGE_LENGTH: usize = 64 * 1024;
+
+/// Field type tags, each followed by the value.
+const U8_FIELD: u8 = 0x0f;
+const U16_FIELD: u8 = 0x10;
+const U32_FIELD: u8 = 0x11;
+const BINARY_FIELD: u8 = 0x14;
+const STRING_FIELD: u8 = 0x26;
+
+/// Argument types as listed in the header of a message.
+const STRING_ARGUMENT: u8 = 0x02;
+const BINARY_ARGUMENT: u8 = 0x03;
+const U8_ARGUMENT: u8 = 0x04;
+const U16_ARGUMENT: u8 = 0x05;
+const U32_ARGUMENT: u8 = 0x06;
+
+/// What a client sends over a remote database connection.
+#[derive(Debug, PartialEq)]
+pub enum DbFrame {
+    Greeting(Bytes),
+    Message(DBMessage),
+    /// A complete message whose arguments can't be read, answered with a
+    /// failure response.
+    Malformed {
+        transaction_id: DBField,
+        request_type: DBRequestType,
+    },
+}
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub struct DbBytesCodec(());
 
 impl DbBytesCodec {
-    /// Creates a new `RpcBytesCodec` for shipping `RpcMessage` back and forth
+    /// Creates a new `DbBytesCodec` for reading requests of players and
+    /// sending back the responses
     pub fn new() -> DbBytesCodec {
         DbBytesCodec(())
     }
 }
 
+fn invalid_data(reason: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, reason.to_string())
+}
+
+/// Length of the field of `argument_type` at the start of `buf`, `None` while
+/// it hasn't been received completely.
+fn field_length(argument_type: u8, buf: &[u8], is_last: bool) -> Result<Option<usize>, Error> {
+    let (tag, length) = match argument_type {
+        U8_ARGUMENT => (U8_FIELD, Some(2)),
+        U16_ARGUMENT => (U16_FIELD, Some(3)),
+        U32_ARGUMENT => (U32_FIELD, Some(5)),
+        STRING_ARGUMENT => (STRING_FIELD, None),
+        BINARY_ARGUMENT => {
+            // Players leave out empty binary arguments altogether, the
+            // message ends or the next argument follows instead.
+            match buf.first() {
+                None if is_last => return Ok(Some(0)),
+                None => return Ok(None),
+                Some(tag) if *tag != BINARY_FIELD => return Ok(Some(0)),
+                Some(_) => (BINARY_FIELD, None),
+            }
+        },
+        _ => return Err(invalid_data("Unknown argument type in DBMessage.")),
+    };
+
+    match buf.first() {
+        None => return Ok(None),
+        Some(actual) if *actual != tag => return Err(invalid_data("Argument doesn't match its type in DBMessage.")),
+        Some(_) => {},
+    }
+
+    if let Some(length) = length {
+        return Ok(Some(length));
+    }
+
+    // Strings and binaries carry their size, strings count UTF-16 units.
+    if buf.len() < 5 {
+        return Ok(None);
+    }
+    let size = u32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]) as usize;
+
+    Ok(Some(5 + if tag == STRING_FIELD { size * 2 } else { size }))
+}
+
+/// Length of the message at the start of `buf`, found by walking its fields.
+/// `None` while the message hasn't been received completely.
+fn message_length(buf: &[u8]) -> Result<Option<usize>, Error> {
+    if buf.len() < HEADER_LENGTH {
+        return Ok(None);
+    }
+
+    let header_fields = [
+        (TRANSACTION_ID_OFFSET, U32_FIELD),
+        (REQUEST_TYPE_OFFSET, U16_FIELD),
+        (ARGUMENT_COUNT_OFFSET - 1, U8_FIELD),
+        (ARGUMENT_COUNT_OFFSET + 1, BINARY_FIELD),
+    ];
+    if header_fields.iter().any(|(offset, tag)| buf[*offset] != *tag) {
+        return Err(invalid_data("Malformed DBMessage header."));
+    }
+
+    let argument_count = buf[ARGUMENT_COUNT_OFFSET] as usize;
+    if argument_count > MAX_ARGUMENTS {
+        return Err(invalid_data("Too many arguments in DBMessage."));
+    }
+
+    let argument_types = &buf[ARGUMENT_TYPES_OFFSET..ARGUMENT_TYPES_OFFSET + argument_count];
+    let mut length = HEADER_LENGTH;
+    for (index, argument_type) in argument_types.iter().enumerate() {
+        let is_last = index + 1 == argument_count;
+
+        match field_length(*argument_type, &buf[std::cmp::min(length, buf.len())..], is_last)? {
+            Some(field) => length += field,
+            None => return Ok(None),
+        }
+
+        if length > MAX_MESSAGE_LENGTH {
+            return Err(invalid_data("DBMessage exceeds the maximum request size."));
+        }
+    }
+
+    Ok(if length <= buf.len() { Some(length) } else { None })
+}
+
 impl Decoder for DbBytesCodec {
-    type Item = DBMessage;
+    type Item = DbFrame;
     type Error = Error;
 
     fn decode(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        if buf.len() > 0 {
-            let len = buf.len();
+        if buf.len() < GREETING_LENGTH {
+            return Ok(None);
+        }
 
-            match DBMessage::try_from(Bytes::from(buf.split_to(len))) {
-                Ok(message) => Ok(Some(message)),
-                Err(err) => Err(Error::new(ErrorKind::InvalidInput, err)),
-            }
-        } else {
-            Ok(None)
+        if buf[0] != U32_FIELD {
+            return Err(invalid_data("DBMessage doesn't start with a number field."));
+        }
+
+        if buf[1..GREETING_LENGTH] != DBMessage::MAGIC {
+            return Ok(Some(DbFrame::Greeting(buf.split_to(GREETING_LENGTH).freeze())));
+        }
+
+        let length = match message_length(buf)? {
+            Some(length) => length,
+            None => {
+                buf.reserve(HEADER_LENGTH);
+                return Ok(None);
+            },
+        };
+
+        let frame = buf.split_to(length).freeze();
+        match DBMessage::parse(&frame) {
+            Ok((_input, message)) => Ok(Some(DbFrame::Message(message))),
+            Err(_err) => {
+                let request_type = &frame[REQUEST_TYPE_OFFSET + 1..REQUEST_TYPE_OFFSET + 3];
+
+                Ok(Some(DbFrame::Malformed {
+                    transaction_id: DBField::new(
+                        DBFieldType::U32,
+                        &frame[TRANSACTION_ID_OFFSET + 1..TRANSACTION_ID_OFFSET + 5],
+                    ),
+                    request_type: DBRequestType::new(u16::from_be_bytes([request_type[0], request_type[1]])),
+                }))
+            },
         }
     }
 }
 
+impl Encoder<Bytes> for DbBytesCodec {
+    type Error = Error;
+
+    fn encode(&mut self, data: Bytes, buf: &mut BytesMut) -> Result<(), Error> {
+        buf.extend(data);
+
+        Ok(())
+    }
+}
+
 impl Encoder<DBMessage> for DbBytesCodec {
     type Error = Error;
 
@@ -43,3 +206,114 @@ impl Encoder<DBMessage> for DbBytesCodec {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rekordbox::db_message_argument::ArgumentCollection;
+
+    fn metadata_request() -> DBMessage {
+        DBMessage::new(
+            DBField::from([0x05, 0x80, 0x00, 0x32]),
+            DBRequestType::MetadataRequest,
+            ArgumentCollection::new(vec![
+                DBField::from([0x02, 0x01, 0x04, 0x01]),
+                DBField::from(42u32),
+            ]),
+        )
+    }
+
+    fn search_request() -> DBMessage {
+        DBMessage::new(
+            DBField::from([0x05, 0x80, 0x00, 0x33]),
+            DBRequestType::SearchQueryRequest,
+            ArgumentCollection::new(vec![
+                DBField::from([0x02, 0x01, 0x04, 0x01]),
+                DBField::from(0u32),
+                DBField::from(4u32),
+                DBField::from("Demo"),
+            ]),
+        )
+    }
+
+    fn decode_all(codec: &mut DbBytesCodec, buf: &mut BytesMut) -> Vec<DbFrame> {
+        let mut frames = vec![];
+        while let Some(frame) = codec.decode(buf).unwrap() {
+            frames.push(frame);
+        }
+        frames
+    }
+
+    #[test]
+    fn it_decodes_pipelined_messages_following_the_greeting() {
+        let mut buf = BytesMut::from(&b"\x11\x00\x00\x00\x01"[..]);
+        buf.extend(Bytes::from(metadata_request()));
+        buf.extend(Bytes::from(search_request()));
+
+        let frames = decode_all(&mut DbBytesCodec::new(), &mut buf);
+
+        assert_eq!(vec![
+            DbFrame::Greeting(Bytes::from_static(b"\x11\x00\x00\x00\x01")),
+            DbFrame::Message(metadata_request()),
+            DbFrame::Message(search_request()),
+        ], frames);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn it_buffers_messages_split_across_reads() {
+        let message = Bytes::from(search_request());
+        let mut codec = DbBytesCodec::new();
+        let mut buf = BytesMut::new();
+
+        for byte in &message[..message.len() - 1] {
+            buf.extend(&[*byte]);
+            assert_eq!(None, codec.decode(&mut buf).unwrap());
+        }
+
+        buf.extend(&message[message.len() - 1..]);
+        assert_eq!(Some(DbFrame::Message(search_request())), codec.decode(&mut buf).unwrap());
+    }
+
+    #[test]
+    fn it_accepts_messages_without_their_trailing_empty_binary() {
+        let mut buf = BytesMut::from(&[
+            0x11, 0x87, 0x23, 0x49, 0xae,
+            0x11, 0x05, 0x80, 0x00, 0x51,
+            0x10, 0x20, 0x04,
+            0x0f, 0x05, 0x14, 0x00, 0x00, 0x00, 0x0c,
+            0x06, 0x06, 0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x11, 0x01, 0x08, 0x04, 0x01,
+            0x11, 0x00, 0x00, 0x00, 0x04,
+            0x11, 0x00, 0x00, 0x00, 0x05,
+            0x11, 0x00, 0x00, 0x00, 0x00,
+        ][..]);
+        buf.extend(Bytes::from(metadata_request()));
+
+        let frames = decode_all(&mut DbBytesCodec::new(), &mut buf);
+
+        assert_eq!(2, frames.len());
+        assert_eq!(DbFrame::Message(metadata_request()), frames[1]);
+    }
+
+    #[test]
+    fn it_reports_messages_with_unreadable_arguments() {
+        let mut message = BytesMut::from(&Bytes::from(search_request())[..]);
+        // A string claiming to hold nothing, not even its terminator
+        let string_size = message.len() - 14;
+        message[string_size..string_size + 4].copy_from_slice(&[0, 0, 0, 0]);
+        message.truncate(string_size + 4);
+
+        assert_eq!(Some(DbFrame::Malformed {
+            transaction_id: DBField::from([0x05, 0x80, 0x00, 0x33]),
+            request_type: DBRequestType::SearchQueryRequest,
+        }), DbBytesCodec::new().decode(&mut message).unwrap());
+    }
+
+    #[test]
+    fn it_rejects_garbage() {
+        let mut buf = BytesMut::from(&b"\x12\x34\x56\x78\x9a\xbc"[..]);
+
+        assert!(DbBytesCodec::new().decode(&mut buf).is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::rekordbox::{Cue, CueKind};
+
+/// Size of an entry in the cue list of players predating the nexus 2.
+const ENTRY_SIZE: usize = 36;
+
+/// Offset of the comment in an extended entry, the colour follows it.
+const EXTENDED_COMMENT_OFFSET: usize = 0x4a;
+
+const FLAG_CUE: u8 = 1;
+const FLAG_LOOP: u8 = 2;
+
+/// Hot cues are numbered from 1 on the wire, 0 is a memory point.
+fn hot_cue_number(cue: &Cue) -> u8 {
+    match cue.kind {
+        CueKind::Memory => 0,
+        CueKind::HotCue(index) => index + 1,
+    }
+}
+
+/// Older players count in half frames, 150 per second.
+fn half_frames(milliseconds: u32) -> u32 {
+    (milliseconds as u64 * 150 / 1000) as u32
+}
+
+/// The cue list answered to the cue list request: fixed size, little endian
+/// entries without colours or comments.
+pub fn cue_list(cues: &[Cue]) -> Bytes {
+    let mut bytes = BytesMut::with_capacity(ENTRY_SIZE * cues.len());
+
+    for cue in cues {
+        let mut entry = [0u8; ENTRY_SIZE];
+        entry[0] = cue.end.is_some() as u8;
+        entry[1] = FLAG_CUE;
+        entry[2] = hot_cue_number(cue);
+        entry[12..16].copy_from_slice(&half_frames(cue.start).to_le_bytes());
+        entry[16..20].copy_from_slice(&half_frames(cue.end.unwrap_or(0)).to_le_bytes());
+
+        bytes.put_slice(&entry);
+    }
+
+    bytes.freeze()
+}
+
+/// The extended cue list nexus 2 and later players ask for when loading a
+/// track: variable size, little endian entries in milliseconds, with an
+/// UTF-16 comment followed by the colour.
+pub fn extended_cue_list(cues: &[Cue]) -> Bytes {
+    let mut bytes = BytesMut::new();
+
+    for cue in cues {
+        let mut comment = BytesMut::new();
+        if !cue.comment.is_empty() {
+            for unit in cue.comment.encode_utf16().chain(std::iter::once(0)) {
+                comment.put_u16_le(unit);
+            }
+        }
+
+        let color_offset = EXTENDED_COMMENT_OFFSET + comment.len() + 4;
+        let size = (color_offset + 4).div_ceil(4) * 4;
+        let mut entry = vec![0u8; size];
+        entry[0..4].copy_from_slice(&(size as u32).to_le_bytes());
+        entry[4] = hot_cue_number(cue);
+        entry[6] = if cue.end.is_some() { FLAG_LOOP } else { FLAG_CUE };
+        entry[12..16].copy_from_slice(&cue.start.to_le_bytes());
+        entry[16..20].copy_from_slice(&cue.end.unwrap_or(0).to_le_bytes());
+        entry[0x48..0x4a].copy_from_slice(&(comment.len() as u16).to_le_bytes());
+        entry[EXTENDED_COMMENT_OFFSET..EXTENDED_COMMENT_OFFSET + comment.len()].copy_from_slice(&comment);
+
+        // Without a colour code players fall back to their default colours,
+        // the RGB value is shown by those that support custom colours.
+        if let Some(rgb) = cue.color {
+            entry[color_offset + 1..color_offset + 4].copy_from_slice(&rgb);
+        }
+
+        bytes.put_slice(&entry);
+    }
+
+    bytes.freeze()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn cues() -> Vec<Cue> {
+        vec![
+            Cue { kind: CueKind::Memory, start: 1000, end: Some(8500), color: None, comment: "".to_string() },
+            Cue {
+                kind: CueKind::HotCue(2),
+                start: 61900,
+                end: None,
+                color: Some([40, 226, 20]),
+                comment: "Drop".to_string(),
+            },
+        ]
+    }
+
+    #[test]
+    fn it_encodes_cues_in_half_frames() {
+        let bytes = cue_list(&cues());
+
+        assert_eq!(2 * 36, bytes.len());
+        assert_eq!(&[1, 1, 0], &bytes[0..3]);
+        assert_eq!(&150u32.to_le_bytes(), &bytes[12..16]);
+        assert_eq!(&1275u32.to_le_bytes(), &bytes[16..20]);
+        assert_eq!(&[0, 1, 3], &bytes[36..39]);
+        assert_eq!(&9285u32.to_le_bytes(), &bytes[48..52]);
+    }
+
+    #[test]
+    fn it_encodes_extended_cues_with_comment_and_colour() {
+        let bytes = extended_cue_list(&cues());
+        let first_size = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        let hot_cue = &bytes[first_size..];
+
+        assert_eq!(0x54, first_size);
+        assert_eq!(2, bytes[6]);
+        assert_eq!(&8500u32.to_le_bytes(), &bytes[16..20]);
+
+        assert_eq!(3, hot_cue[4]);
+        assert_eq!(1, hot_cue[6]);
+        assert_eq!(&61900u32.to_le_bytes(), &hot_cue[12..16]);
+        assert_eq!(&[10, 0], &hot_cue[0x48..0x4a]);
+        assert_eq!(&[b'D', 0, b'r', 0], &hot_cue[0x4a..0x4e]);
+        assert_eq!(&[40, 226, 20], &hot_cue[0x4e + 10 + 1..0x4e + 10 + 4]);
+        assert_eq!(hot_cue.len(), u32::from_le_bytes([hot_cue[0], hot_cue[1], hot_cue[2], hot_cue[3]]) as usize);
+    }
+}
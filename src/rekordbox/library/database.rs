@@ -1,10 +1,34 @@
+use std::borrow::Cow;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock, RwLockWriteGuard, RwLockReadGuard, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::ops::Add;
+use std::time::SystemTime;
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
 
-use crate::rekordbox::MetadataTrack;
-use crate::library::scan_folder;
+use crate::rekordbox::{Color, Cue, MetadataTrack};
+use crate::utils::text::{fold, sort_key};
+use super::analysis_cache::{AnalysisCache, AnalysisState};
+use super::history::History;
+use super::store;
+use crate::library::{
+    audio_files,
+    audio_files_below,
+    normalize_path,
+    read_collection_cues,
+    read_track,
+    scan_playlists,
+    Analysis,
+    AudioFile,
+    AudioFormat,
+    Key,
+    LibraryRoot,
+    PlaylistFile,
+    ScanOptions,
+};
 
 #[derive(Debug)]
 pub enum DatabaseError {
@@ -16,32 +40,107 @@ struct ArtistTable<T: Record> {
     sequence: Sequence<u32>,
 }
 
+struct AlbumTable<T: Record> {
+    rows: HashMap<u32, T>,
+    sequence: Sequence<u32>,
+}
+
+struct GenreTable<T: Record> {
+    rows: HashMap<u32, T>,
+    sequence: Sequence<u32>,
+}
+
+struct KeyTable<T: Record> {
+    rows: HashMap<u32, T>,
+    sequence: Sequence<u32>,
+}
+
+struct PlaylistTable<T: Record> {
+    rows: HashMap<u32, T>,
+    sequence: Sequence<u32>,
+}
+
 struct TrackTable<T: Record> {
     rows: HashMap<u32, T>,
     sequence: Sequence<u32>,
 }
 
+struct ArtworkTable<T: Record> {
+    rows: HashMap<u32, T>,
+    sequence: Sequence<u32>,
+}
+
+/// Album id of tracks without an album tag, presented as "Unknown" by the players.
+pub const UNKNOWN_ALBUM_ID: u32 = 0;
+
+/// Genre id of tracks without a genre tag.
+pub const UNKNOWN_GENRE_ID: u32 = 0;
+
+/// Key id of tracks without a known key.
+pub const UNKNOWN_KEY_ID: u32 = 0;
+
+/// Artwork id of tracks without a cover image, players show a placeholder.
+pub const NO_ARTWORK_ID: u32 = 0;
+
+/// Id of the folder holding every top level playlist.
+pub const ROOT_PLAYLIST_FOLDER_ID: u32 = 0;
+
 struct NewTrack {
+    /// Id of the row this replaces when a changed file is read again.
+    id: Option<u32>,
     artist_id: u32,
+    album_id: u32,
+    genre_id: u32,
+    key_id: u32,
     title: String,
     path: PathBuf,
     size: u32,
     bpm: Option<u32>,
+    duration: Option<u32>,
+    genre: String,
+    key: String,
+    label: String,
+    year: Option<u32>,
+    comment: String,
+    rating: Option<u8>,
+    color: Option<Color>,
+    artwork_id: u32,
+    cues: Vec<Cue>,
+    date_added: Option<SystemTime>,
+    modified: Option<SystemTime>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
     id: u32,
     pub artist_id: u32,
+    pub album_id: u32,
+    pub genre_id: u32,
+    pub key_id: u32,
     title: String,
     pub path: PathBuf,
     pub size: u32,
     pub bpm: Option<u32>,
+    pub duration: Option<u32>,
+    pub genre: String,
+    pub key: String,
+    pub label: String,
+    pub year: Option<u32>,
+    pub comment: String,
+    pub rating: Option<u8>,
+    pub color: Option<Color>,
+    pub artwork_id: u32,
+    pub cues: Vec<Cue>,
+    /// When the file was created, or last modified if the filesystem doesn't record creation.
+    pub date_added: Option<SystemTime>,
+    /// Modification time of the file when its tags were read.
+    pub modified: Option<SystemTime>,
 }
 
 impl Track {
-    pub fn path(&self) -> &str {
-        self.path.to_str().unwrap()
+    /// The path as text, names that aren't valid UTF-8 get replacement characters.
+    pub fn path(&self) -> Cow<'_, str> {
+        self.path.to_string_lossy()
     }
 }
 
@@ -49,12 +148,95 @@ struct NewArtist {
     name: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Artist {
     id: u32,
     name: String,
 }
 
+struct NewAlbum {
+    name: String,
+    artist_id: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Album {
+    id: u32,
+    name: String,
+    pub artist_id: u32,
+}
+
+struct NewGenre {
+    name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Genre {
+    id: u32,
+    name: String,
+}
+
+struct NewKey {
+    name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MusicalKey {
+    id: u32,
+    name: String,
+    key: Key,
+}
+
+impl MusicalKey {
+    pub fn key(&self) -> &Key {
+        &self.key
+    }
+}
+
+struct NewPlaylist {
+    name: String,
+    parent_id: u32,
+    is_folder: bool,
+    track_ids: Vec<u32>,
+}
+
+struct NewArtwork {
+    data: Bytes,
+}
+
+/// A scaled down cover image, shared by every track with the same image.
+/// Its name is a hash of the image, which is how duplicates are found.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredArtwork {
+    id: u32,
+    name: String,
+    pub data: Bytes,
+}
+
+/// Playlists and playlist folders share one id space, like they do in rekordbox.
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    id: u32,
+    name: String,
+    pub parent_id: u32,
+    pub is_folder: bool,
+    pub track_ids: Vec<u32>,
+}
+
+/// A recorded set from the play history, ids are assigned in chronological order.
+#[derive(Debug, Clone)]
+pub struct HistorySession {
+    id: u32,
+    name: String,
+}
+
+/// Order records by name the way a reader expects, ignoring case, accents and
+/// a leading "The ", ties are broken by id so the order is stable.
+pub fn sorted_by_name<T: Record>(mut records: Vec<T>) -> Vec<T> {
+    records.sort_by_cached_key(|record| (sort_key(record.name()), *record.id()));
+    records
+}
+
 pub trait Record {
     fn name(&self) -> &String;
     fn id(&self) -> &u32;
@@ -80,6 +262,66 @@ impl Record for Artist {
     }
 }
 
+impl Record for Album {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn id(&self) -> &u32 {
+        &self.id
+    }
+}
+
+impl Record for Genre {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn id(&self) -> &u32 {
+        &self.id
+    }
+}
+
+impl Record for MusicalKey {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn id(&self) -> &u32 {
+        &self.id
+    }
+}
+
+impl Record for StoredArtwork {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn id(&self) -> &u32 {
+        &self.id
+    }
+}
+
+impl Record for Playlist {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn id(&self) -> &u32 {
+        &self.id
+    }
+}
+
+impl Record for HistorySession {
+    fn name(&self) -> &String {
+        &self.name
+    }
+
+    fn id(&self) -> &u32 {
+        &self.id
+    }
+}
+
 impl Insertable<NewArtist, u32> for ArtistTable<Artist> {
     fn insert(&mut self, document: NewArtist) -> u32 {
         for (id, value) in self.rows.iter() {
@@ -101,17 +343,169 @@ impl Insertable<NewArtist, u32> for ArtistTable<Artist> {
     }
 }
 
+impl Insertable<NewAlbum, u32> for AlbumTable<Album> {
+    fn insert(&mut self, document: NewAlbum) -> u32 {
+        if document.name.is_empty() {
+            return UNKNOWN_ALBUM_ID;
+        }
+
+        for (id, value) in self.rows.iter() {
+            if document.name == value.name && document.artist_id == value.artist_id {
+                return *id;
+            }
+        }
+
+        match self.sequence.increment() {
+            Ok(id) => {
+                self.rows.insert(id, Album {
+                    id,
+                    name: document.name,
+                    artist_id: document.artist_id,
+                });
+                id
+            },
+            Err(err) => panic!("Failed inserting document into AlbumTable; error = {}", err),
+        }
+    }
+}
+
+impl Insertable<NewGenre, u32> for GenreTable<Genre> {
+    fn insert(&mut self, document: NewGenre) -> u32 {
+        if document.name.is_empty() {
+            return UNKNOWN_GENRE_ID;
+        }
+
+        for (id, value) in self.rows.iter() {
+            if document.name == value.name {
+                return *id;
+            }
+        }
+
+        match self.sequence.increment() {
+            Ok(id) => {
+                self.rows.insert(id, Genre {
+                    id,
+                    name: document.name,
+                });
+                id
+            },
+            Err(err) => panic!("Failed inserting document into GenreTable; error = {}", err),
+        }
+    }
+}
+
+impl Insertable<NewKey, u32> for KeyTable<MusicalKey> {
+    fn insert(&mut self, document: NewKey) -> u32 {
+        // Keys are normalised by the scanner, anything else is treated as unknown.
+        let key = match Key::parse(&document.name) {
+            Some(key) => key,
+            None => return UNKNOWN_KEY_ID,
+        };
+
+        for (id, value) in self.rows.iter() {
+            if key == value.key {
+                return *id;
+            }
+        }
+
+        match self.sequence.increment() {
+            Ok(id) => {
+                self.rows.insert(id, MusicalKey {
+                    id,
+                    name: key.to_string(),
+                    key,
+                });
+                id
+            },
+            Err(err) => panic!("Failed inserting document into KeyTable; error = {}", err),
+        }
+    }
+}
+
+impl Insertable<NewArtwork, u32> for ArtworkTable<StoredArtwork> {
+    fn insert(&mut self, document: NewArtwork) -> u32 {
+        let mut hasher = DefaultHasher::new();
+        document.data.hash(&mut hasher);
+        let name = format!("{:016x}", hasher.finish());
+
+        for (id, value) in self.rows.iter() {
+            if name == value.name && document.data == value.data {
+                return *id;
+            }
+        }
+
+        match self.sequence.increment() {
+            Ok(id) => {
+                self.rows.insert(id, StoredArtwork {
+                    id,
+                    name,
+                    data: document.data,
+                });
+                id
+            },
+            Err(err) => panic!("Failed inserting document into ArtworkTable; error = {}", err),
+        }
+    }
+}
+
+impl Insertable<NewPlaylist, u32> for PlaylistTable<Playlist> {
+    fn insert(&mut self, document: NewPlaylist) -> u32 {
+        // Folders are created once for every playlist below them.
+        if document.is_folder {
+            for (id, value) in self.rows.iter() {
+                if value.is_folder && document.name == value.name && document.parent_id == value.parent_id {
+                    return *id;
+                }
+            }
+        }
+
+        match self.sequence.increment() {
+            Ok(id) => {
+                self.rows.insert(id, Playlist {
+                    id,
+                    name: document.name,
+                    parent_id: document.parent_id,
+                    is_folder: document.is_folder,
+                    track_ids: document.track_ids,
+                });
+                id
+            },
+            Err(err) => panic!("Failed inserting document into PlaylistTable; error = {}", err),
+        }
+    }
+}
+
 impl Insertable<NewTrack, u32> for TrackTable<Track> {
     fn insert(&mut self, document: NewTrack) -> u32 {
-        match self.sequence.increment() {
+        let id = match document.id {
+            Some(id) => Ok(id),
+            None => self.sequence.increment(),
+        };
+
+        match id {
             Ok(id) => {
                 self.rows.insert(id.clone(), Track {
                     id,
                     artist_id: document.artist_id,
+                    album_id: document.album_id,
+                    genre_id: document.genre_id,
+                    key_id: document.key_id,
                     path: document.path,
                     title: document.title,
                     size: document.size,
                     bpm: document.bpm,
+                    duration: document.duration,
+                    genre: document.genre,
+                    key: document.key,
+                    label: document.label,
+                    year: document.year,
+                    comment: document.comment,
+                    rating: document.rating,
+                    color: document.color,
+                    artwork_id: document.artwork_id,
+                    cues: document.cues,
+                    date_added: document.date_added,
+                    modified: document.modified,
                 });
                 return id;
             },
@@ -130,6 +524,51 @@ impl<T: Record> ArtistTable<T> {
     }
 }
 
+impl<T: Record> AlbumTable<T> {
+    fn new() -> Self {
+        Self {
+            rows: HashMap::new(),
+            sequence: Sequence::new(),
+        }
+    }
+}
+
+impl<T: Record> GenreTable<T> {
+    fn new() -> Self {
+        Self {
+            rows: HashMap::new(),
+            sequence: Sequence::new(),
+        }
+    }
+}
+
+impl<T: Record> KeyTable<T> {
+    fn new() -> Self {
+        Self {
+            rows: HashMap::new(),
+            sequence: Sequence::new(),
+        }
+    }
+}
+
+impl<T: Record> PlaylistTable<T> {
+    fn new() -> Self {
+        Self {
+            rows: HashMap::new(),
+            sequence: Sequence::new(),
+        }
+    }
+}
+
+impl<T: Record> ArtworkTable<T> {
+    fn new() -> Self {
+        Self {
+            rows: HashMap::new(),
+            sequence: Sequence::new(),
+        }
+    }
+}
+
 impl<T: Record> TrackTable<T> {
     fn new() -> Self {
         Self {
@@ -160,10 +599,21 @@ impl<T: PrimaryKey> Sequence<T> {
 }
 
 impl<T: PrimaryKey + Copy> Sequence<T> {
-    fn increment(&mut self) -> Result<T, &'static str> {
-        match self.counter.clone().lock() {
-            Ok(mut value) => {
-                *value = *value + T::one();
+    /// Continue counting after ids handed out in an earlier run.
+    fn starting_after(last: T) -> Self {
+        Self {
+            counter: Arc::new(Mutex::new(last)),
+        }
+    }
+
+    fn last(&self) -> Option<T> {
+        self.counter.lock().ok().map(|value| *value)
+    }
+
+    fn increment(&mut self) -> Result<T, &'static str> {
+        match self.counter.clone().lock() {
+            Ok(mut value) => {
+                *value = *value + T::one();
                 Ok(*value)
             },
             Err(_) => Err("Allocation lock for id sequence failed."),
@@ -179,33 +629,486 @@ impl PrimaryKey for u32 {
     fn one() -> Self { 1 }
 }
 
+/// Rows of a table along with the last id handed out, so ids of removed rows
+/// aren't given to new ones.
+#[derive(Serialize, Deserialize)]
+struct TableSnapshot<T> {
+    last_id: u32,
+    rows: Vec<T>,
+}
+
+impl<T: Record + Clone> TableSnapshot<T> {
+    fn new(rows: &HashMap<u32, T>, sequence: &Sequence<u32>) -> Self {
+        Self {
+            last_id: sequence.last().unwrap_or_else(u32::one),
+            rows: rows.values().cloned().collect(),
+        }
+    }
+
+    fn restore(self) -> (HashMap<u32, T>, Sequence<u32>) {
+        let rows = self.rows.into_iter().map(|row| (*row.id(), row)).collect();
+
+        (rows, Sequence::starting_after(self.last_id))
+    }
+}
+
+/// The scanned library as stored between runs. Playlists are cheap to read
+/// and are scanned on every start instead.
+#[derive(Serialize, Deserialize)]
+struct Snapshot {
+    artists: TableSnapshot<Artist>,
+    albums: TableSnapshot<Album>,
+    genres: TableSnapshot<Genre>,
+    keys: TableSnapshot<MusicalKey>,
+    artworks: TableSnapshot<StoredArtwork>,
+    tracks: TableSnapshot<Track>,
+    scanned_with: ScanOptions,
+}
+
 struct InnerDatabase {
     artists: ArtistTable<Artist>,
+    albums: AlbumTable<Album>,
+    genres: GenreTable<Genre>,
+    keys: KeyTable<MusicalKey>,
+    artworks: ArtworkTable<StoredArtwork>,
+    playlists: PlaylistTable<Playlist>,
     tracks: TrackTable<Track>,
+    history: History,
+    /// Cue points from rekordbox collection exports, for tracks without their own.
+    collection_cues: HashMap<PathBuf, Vec<Cue>>,
+    /// Options the tracks were read with, they're read again when these change.
+    scanned_with: ScanOptions,
+}
+
+impl InnerDatabase {
+    fn new() -> Self {
+        Self {
+            artists: ArtistTable::new(),
+            albums: AlbumTable::new(),
+            genres: GenreTable::new(),
+            keys: KeyTable::new(),
+            artworks: ArtworkTable::new(),
+            playlists: PlaylistTable::new(),
+            tracks: TrackTable::new(),
+            history: History::default(),
+            collection_cues: HashMap::new(),
+            scanned_with: ScanOptions::default(),
+        }
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            artists: TableSnapshot::new(&self.artists.rows, &self.artists.sequence),
+            albums: TableSnapshot::new(&self.albums.rows, &self.albums.sequence),
+            genres: TableSnapshot::new(&self.genres.rows, &self.genres.sequence),
+            keys: TableSnapshot::new(&self.keys.rows, &self.keys.sequence),
+            artworks: TableSnapshot::new(&self.artworks.rows, &self.artworks.sequence),
+            tracks: TableSnapshot::new(&self.tracks.rows, &self.tracks.sequence),
+            scanned_with: self.scanned_with.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        let (rows, sequence) = snapshot.artists.restore();
+        self.artists = ArtistTable { rows, sequence };
+        let (rows, sequence) = snapshot.albums.restore();
+        self.albums = AlbumTable { rows, sequence };
+        let (rows, sequence) = snapshot.genres.restore();
+        self.genres = GenreTable { rows, sequence };
+        let (rows, sequence) = snapshot.keys.restore();
+        self.keys = KeyTable { rows, sequence };
+        let (rows, sequence) = snapshot.artworks.restore();
+        self.artworks = ArtworkTable { rows, sequence };
+        let (rows, sequence) = snapshot.tracks.restore();
+        self.tracks = TrackTable { rows, sequence };
+        self.scanned_with = snapshot.scanned_with;
+    }
+
+    /// Insert a track read from disk. A file that was read before keeps its id
+    /// and date added, so players and the history keep pointing at it.
+    fn index_track(&mut self, track: MetadataTrack, modified: Option<SystemTime>, previous: Option<Track>) -> u32 {
+        let metadata = track.metadata;
+        let artist_id = self.artists.insert(NewArtist {
+            name: metadata.artist,
+        });
+        let album_id = self.albums.insert(NewAlbum {
+            name: metadata.album,
+            artist_id,
+        });
+        let genre_id = self.genres.insert(NewGenre {
+            name: metadata.genre.clone(),
+        });
+        let key_id = self.keys.insert(NewKey {
+            name: metadata.key.clone(),
+        });
+        let artwork_id = match metadata.artwork {
+            Some(artwork) => self.artworks.insert(NewArtwork {
+                data: artwork.data,
+            }),
+            None => NO_ARTWORK_ID,
+        };
+        let date_added = match &previous {
+            Some(previous) => previous.date_added,
+            None => std::fs::metadata(&track.path)
+                .and_then(|metadata| metadata.created().or_else(|_| metadata.modified()))
+                .ok(),
+        };
+
+        self.tracks.insert(NewTrack {
+            id: previous.map(|previous| previous.id),
+            artist_id,
+            album_id,
+            genre_id,
+            key_id,
+            path: track.path,
+            title: metadata.title,
+            size: track.size,
+            bpm: metadata.bpm,
+            duration: metadata.duration,
+            genre: metadata.genre,
+            key: metadata.key,
+            label: metadata.label,
+            year: metadata.year,
+            comment: metadata.comment,
+            rating: metadata.rating,
+            color: metadata.color,
+            artwork_id,
+            cues: metadata.cues,
+            date_added,
+            modified,
+        })
+    }
+
+    fn index_playlist(&mut self, playlist: PlaylistFile) {
+        let mut parent_id = ROOT_PLAYLIST_FOLDER_ID;
+        for folder in playlist.folders {
+            parent_id = self.playlists.insert(NewPlaylist {
+                name: folder,
+                parent_id,
+                is_folder: true,
+                track_ids: vec![],
+            });
+        }
+
+        let track_ids_by_path = self.tracks.rows.values()
+            .map(|track| (normalize_path(&track.path), track.id))
+            .collect::<HashMap<PathBuf, u32>>();
+
+        // Entries pointing outside the library, or to missing files, are dropped.
+        let track_ids = playlist.entries.iter()
+            .filter_map(|entry| track_ids_by_path.get(entry).copied())
+            .collect();
+
+        self.playlists.insert(NewPlaylist {
+            name: playlist.name,
+            parent_id,
+            is_folder: false,
+            track_ids,
+        });
+    }
+
+    /// Drop artists, albums, genres, keys and artwork no track refers to anymore.
+    fn prune(&mut self) {
+        let tracks = self.tracks.rows.values();
+        let artwork_ids = tracks.clone().map(|track| track.artwork_id).collect::<HashSet<u32>>();
+        let artist_ids = tracks.clone().map(|track| track.artist_id).collect::<HashSet<u32>>();
+        let album_ids = tracks.clone().map(|track| track.album_id).collect::<HashSet<u32>>();
+        let genre_ids = tracks.clone().map(|track| track.genre_id).collect::<HashSet<u32>>();
+        let key_ids = tracks.map(|track| track.key_id).collect::<HashSet<u32>>();
+
+        self.artists.rows.retain(|id, _| artist_ids.contains(id));
+        self.albums.rows.retain(|id, _| album_ids.contains(id));
+        self.genres.rows.retain(|id, _| genre_ids.contains(id));
+        self.keys.rows.retain(|id, _| key_ids.contains(id));
+        self.artworks.rows.retain(|id, _| artwork_ids.contains(id));
+    }
 }
 
 pub struct Database {
     inner: RwLock<InnerDatabase>,
+    roots: Vec<LibraryRoot>,
+    options: ScanOptions,
+    /// File the scanned library is kept in between runs.
+    store: Option<PathBuf>,
+    analyses: AnalysisCache,
 }
 
 impl Database {
     pub fn new<T: AsRef<Path>>(root_folder: T) -> Self {
-        let inner_db = InnerDatabase {
-            artists: ArtistTable::new(),
-            tracks: TrackTable::new(),
-        };
+        Self::with_options(root_folder, &ScanOptions::default())
+    }
+
+    pub fn with_options<T: AsRef<Path>>(root_folder: T, options: &ScanOptions) -> Self {
+        Self::open(vec![LibraryRoot::new(root_folder)], options, None)
+    }
+
+    /// Open the library spread over `roots`. Tracks stored in `store` by an
+    /// earlier run are only read again when their file changed, tracks no
+    /// longer below any of the roots are dropped.
+    pub fn open(roots: Vec<LibraryRoot>, options: &ScanOptions, store: Option<PathBuf>) -> Self {
+        let mut inner_db = InnerDatabase::new();
+
+        if let Some(snapshot) = store.as_ref().and_then(|file| store::load::<Snapshot>(file)) {
+            inner_db.restore(snapshot);
+        }
 
         let database = Self {
             inner: RwLock::new(inner_db),
+            roots,
+            options: options.clone(),
+            store,
+            analyses: AnalysisCache::default(),
         };
 
-        for track in scan_folder(&root_folder) {
-            database.index(track);
+        if let Err(err) = database.rescan() {
+            eprintln!("Failed scanning library; error = {:?}", err);
         }
 
         database
     }
 
+    pub fn roots(&self) -> &[LibraryRoot] {
+        &self.roots
+    }
+
+    /// Bring the tracks and playlists in line with the files below the root
+    /// folders, only new and changed audio files are read. Every file is read
+    /// again when the scan options changed, keys are only detected then.
+    pub fn rescan(&self) -> Result<(), DatabaseError> {
+        let mut known = HashMap::new();
+        self.read(&mut |reader| {
+            if reader.scanned_with != self.options {
+                return;
+            }
+
+            known = reader.tracks.rows.values()
+                .map(|track| (track.path.clone(), (track.size, track.modified)))
+                .collect::<HashMap<PathBuf, (u32, Option<SystemTime>)>>();
+        });
+
+        // Tags are read without holding the lock, the players keep browsing meanwhile.
+        let mut unchanged = HashSet::new();
+        let mut changed = vec![];
+        let mut seen = HashSet::new();
+        // Roots may overlap, a file is only indexed once.
+        let files = self.roots.iter()
+            .flat_map(audio_files)
+            .filter(|file| seen.insert(file.path.clone()));
+
+        for file in files {
+            if known.get(&file.path) == Some(&(file.size, file.modified)) {
+                unchanged.insert(file.path);
+            } else if let Some(track) = read_track(&file, &self.options) {
+                changed.push((track, file));
+            }
+        }
+
+        self.write(|db| {
+            let stale = db.tracks.rows.values()
+                .filter(|track| !unchanged.contains(&track.path))
+                .map(|track| track.id)
+                .collect::<Vec<u32>>();
+
+            let mut previous = HashMap::new();
+            for track_id in stale {
+                if let Some(track) = db.tracks.rows.remove(&track_id) {
+                    previous.insert(track.path.clone(), track);
+                }
+            }
+
+            for (track, file) in changed {
+                let AudioFile { path, modified, .. } = file;
+                db.index_track(track, modified, previous.remove(&path));
+            }
+
+            db.prune();
+            db.scanned_with = self.options.clone();
+            Ok(())
+        })?;
+
+        self.save();
+        self.reindex_playlists()?;
+        self.reindex_collection_cues()
+    }
+
+    /// Bring the tracks at or below `paths` in line with the disk, for files
+    /// and folders created, changed, renamed or removed since the last scan.
+    pub fn update(&self, paths: &[PathBuf]) -> Result<(), DatabaseError> {
+        let mut seen = HashSet::new();
+        let files = self.roots.iter()
+            .flat_map(|root| {
+                paths.iter()
+                    .filter(move |path| path.starts_with(&root.path))
+                    .flat_map(move |path| audio_files_below(root, path))
+            })
+            .filter(|file| seen.insert(file.path.clone()))
+            .collect::<Vec<AudioFile>>();
+
+        let changed = files.into_iter()
+            .filter_map(|file| Some((read_track(&file, &self.options)?, file)))
+            .collect::<Vec<(MetadataTrack, AudioFile)>>();
+
+        // Playlists only need resolving again when tracks come or go, or
+        // when something other than an audio file changed.
+        let mut reindex = paths.iter().any(|path| AudioFormat::from_path(path).is_none());
+        self.write(|db| {
+            let stale = db.tracks.rows.values()
+                .filter(|track| paths.iter().any(|path| track.path.starts_with(path)))
+                .map(|track| track.id)
+                .collect::<Vec<u32>>();
+
+            let mut previous = HashMap::new();
+            for track_id in stale {
+                if let Some(track) = db.tracks.rows.remove(&track_id) {
+                    previous.insert(track.path.clone(), track);
+                }
+            }
+
+            for (track, file) in changed {
+                let AudioFile { path, modified, .. } = file;
+                let previous = previous.remove(&path);
+                reindex |= previous.is_none();
+                db.index_track(track, modified, previous);
+            }
+
+            reindex |= !previous.is_empty();
+            db.prune();
+            Ok(())
+        })?;
+
+        self.save();
+        if !reindex {
+            return Ok(());
+        }
+
+        self.reindex_playlists()?;
+        self.reindex_collection_cues()
+    }
+
+    /// Playlists refer to tracks by path, they are read again after every scan.
+    fn reindex_playlists(&self) -> Result<(), DatabaseError> {
+        let playlists = self.roots.iter()
+            .flat_map(|root| scan_playlists(&root.path))
+            .collect::<Vec<PlaylistFile>>();
+
+        self.write(|db| {
+            db.playlists = PlaylistTable::new();
+            for playlist in playlists {
+                db.index_playlist(playlist);
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Collection exports are read again on every scan, like playlists.
+    fn reindex_collection_cues(&self) -> Result<(), DatabaseError> {
+        let cues = self.roots.iter()
+            .flat_map(|root| read_collection_cues(&root.path))
+            .collect::<HashMap<PathBuf, Vec<Cue>>>();
+
+        self.write(|db| {
+            db.collection_cues = cues;
+
+            Ok(())
+        })
+    }
+
+    fn save(&self) {
+        let file = match &self.store {
+            Some(file) => file,
+            None => return,
+        };
+
+        let mut result = Ok(());
+        self.read(&mut |reader| {
+            result = store::save(file, &reader.snapshot());
+        });
+
+        if let Err(err) = result {
+            eprintln!("Failed saving library database {}; error = {}", file.display(), err);
+        }
+    }
+
+    /// Keep the play history of the players in `history`.
+    pub fn with_history(self, history: History) -> Self {
+        if let Ok(mut writer) = self.inner.write() {
+            writer.history = history;
+        }
+
+        self
+    }
+
+    /// Keep the analyses of the tracks' audio in `analyses`.
+    pub fn with_analysis_cache(mut self, analyses: AnalysisCache) -> Self {
+        self.analyses = analyses;
+        self
+    }
+
+    /// How far the analysis of the audio of a track got, `None` for unknown
+    /// tracks. Never decodes the track, see `analyse`.
+    pub fn analysis(&self, track_id: u32) -> Option<AnalysisState> {
+        Some(self.analyses.state(&self.get_track(track_id)?))
+    }
+
+    /// Claim a track for `analyse`, false when it's being analysed already.
+    pub fn queue_analysis(&self, track_id: u32) -> bool {
+        self.analyses.queue(track_id)
+    }
+
+    /// Release the claim taken with `queue_analysis`.
+    pub fn dequeue_analysis(&self, track_id: u32) {
+        self.analyses.dequeue(track_id)
+    }
+
+    /// Analysis of the audio of a track, the track is decoded when it wasn't
+    /// analysed before.
+    pub fn analyse(&self, track_id: u32) -> Option<Analysis> {
+        self.analyses.analysis(&self.get_track(track_id)?)
+    }
+
+    /// Analyse every track that wasn't analysed yet, so players get beat
+    /// grids and waveforms without waiting for the track to be decoded.
+    pub fn analyse_tracks(&self) {
+        if !self.analyses.is_persistent() {
+            return;
+        }
+
+        for track in self.tracks() {
+            // Tracks a player asked for are analysed by the request already.
+            if self.analyses.queue(*track.id()) {
+                self.analyses.prepare(&track);
+                self.analyses.dequeue(*track.id());
+            }
+        }
+    }
+
+    /// Cue points stored in the track's tags, or else those from a rekordbox
+    /// collection export.
+    pub fn cues(&self, track_id: u32) -> Vec<Cue> {
+        let mut cues = vec![];
+        self.read(&mut |reader| {
+            if let Some(track) = reader.tracks.rows.get(&track_id) {
+                cues = match reader.collection_cues.get(&normalize_path(&track.path)) {
+                    Some(imported) if track.cues.is_empty() => imported.clone(),
+                    _ => track.cues.clone(),
+                };
+            }
+        });
+
+        cues
+    }
+
+    /// The scaled down cover image stored under `artwork_id`.
+    pub fn artwork(&self, artwork_id: u32) -> Option<Bytes> {
+        let mut artwork = None;
+        self.read(&mut |reader| {
+            artwork = reader.artworks.rows.get(&artwork_id).map(|artwork| artwork.data.clone());
+        });
+
+        artwork
+    }
+
     pub fn get_track(&self, track_id: u32) -> Option<Track> {
         let mut ret = None;
         self.read(&mut |reader| {
@@ -228,7 +1131,7 @@ impl Database {
             }
         });
 
-        ret
+        sorted_by_name(ret)
     }
 
     pub fn get_artist(&self, artist_id: u32) -> Option<Artist> {
@@ -245,6 +1148,323 @@ impl Database {
         ret
     }
 
+    pub fn albums(&self) -> Vec<Album> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for album in reader.albums.rows.values() {
+                ret.push(album.clone());
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    pub fn get_album(&self, album_id: u32) -> Option<Album> {
+        let mut ret = None;
+        self.read(&mut |reader| {
+            ret = reader.albums.rows.get(&album_id).cloned();
+        });
+
+        ret
+    }
+
+    pub fn album_by_artist(&self, artist_id: u32) -> Vec<Album> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for album in reader.albums.rows.values() {
+                if album.artist_id == artist_id {
+                    ret.push(album.clone());
+                }
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    /// Whether the artist has tracks that are not part of any album.
+    pub fn has_unknown_album(&self, artist_id: u32) -> bool {
+        let mut ret = false;
+        self.read(&mut |reader| {
+            ret = reader.tracks.rows.values().any(|track| {
+                track.artist_id == artist_id && track.album_id == UNKNOWN_ALBUM_ID
+            });
+        });
+
+        ret
+    }
+
+    pub fn title_by_album(&self, album_id: u32) -> Vec<Track> {
+        let mut titles = vec![];
+        self.read(&mut |reader| {
+            for track in reader.tracks.rows.values() {
+                if track.album_id == album_id {
+                    titles.push(track.clone());
+                }
+            }
+        });
+
+        sorted_by_name(titles)
+    }
+
+    pub fn title_by_artist_album(&self, artist_id: u32, album_id: u32) -> Vec<Track> {
+        let mut titles = vec![];
+        self.read(&mut |reader| {
+            for track in reader.tracks.rows.values() {
+                if track.artist_id == artist_id && track.album_id == album_id {
+                    titles.push(track.clone());
+                }
+            }
+        });
+
+        sorted_by_name(titles)
+    }
+
+    pub fn genres(&self) -> Vec<Genre> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for genre in reader.genres.rows.values() {
+                ret.push(genre.clone());
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    pub fn get_genre(&self, genre_id: u32) -> Option<Genre> {
+        let mut ret = None;
+        self.read(&mut |reader| {
+            ret = reader.genres.rows.get(&genre_id).cloned();
+        });
+
+        ret
+    }
+
+    pub fn artist_by_genre(&self, genre_id: u32) -> Vec<Artist> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            let artist_ids = reader.tracks.rows.values()
+                .filter(|track| track.genre_id == genre_id)
+                .map(|track| track.artist_id)
+                .collect::<HashSet<u32>>();
+
+            for artist_id in artist_ids {
+                if let Some(artist) = reader.artists.rows.get(&artist_id) {
+                    ret.push(artist.clone());
+                }
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    pub fn album_by_genre_artist(&self, genre_id: u32, artist_id: u32) -> Vec<Album> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            let album_ids = reader.tracks.rows.values()
+                .filter(|track| track.genre_id == genre_id && track.artist_id == artist_id)
+                .map(|track| track.album_id)
+                .collect::<HashSet<u32>>();
+
+            for album_id in album_ids {
+                if let Some(album) = reader.albums.rows.get(&album_id) {
+                    ret.push(album.clone());
+                }
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    pub fn title_by_genre_artist_album(&self, genre_id: u32, artist_id: u32, album_id: u32) -> Vec<Track> {
+        let mut titles = vec![];
+        self.read(&mut |reader| {
+            for track in reader.tracks.rows.values() {
+                if track.genre_id == genre_id
+                    && track.artist_id == artist_id
+                    && track.album_id == album_id
+                {
+                    titles.push(track.clone());
+                }
+            }
+        });
+
+        sorted_by_name(titles)
+    }
+
+    pub fn keys(&self) -> Vec<MusicalKey> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for key in reader.keys.rows.values() {
+                ret.push(key.clone());
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    pub fn get_key(&self, key_id: u32) -> Option<MusicalKey> {
+        let mut ret = None;
+        self.read(&mut |reader| {
+            ret = reader.keys.rows.get(&key_id).cloned();
+        });
+
+        ret
+    }
+
+    /// Tracks in keys at most `distance` steps away on the Camelot wheel.
+    pub fn title_by_key_distance(&self, key_id: u32, distance: u32) -> Vec<Track> {
+        let mut titles = vec![];
+        self.read(&mut |reader| {
+            let origin = match reader.keys.rows.get(&key_id) {
+                Some(origin) => origin.key,
+                None => return,
+            };
+
+            for track in reader.tracks.rows.values() {
+                if let Some(key) = reader.keys.rows.get(&track.key_id) {
+                    if origin.distance(&key.key) as u32 <= distance {
+                        titles.push(track.clone());
+                    }
+                }
+            }
+        });
+
+        sorted_by_name(titles)
+    }
+
+    /// Number of playlists, folders excluded.
+    pub fn playlist_count(&self) -> u32 {
+        let mut ret = 0;
+        self.read(&mut |reader| {
+            ret = reader.playlists.rows.values().filter(|playlist| !playlist.is_folder).count() as u32;
+        });
+
+        ret
+    }
+
+    pub fn track_count(&self) -> u32 {
+        let mut ret = 0;
+        self.read(&mut |reader| {
+            ret = reader.tracks.rows.len() as u32;
+        });
+
+        ret
+    }
+
+    pub fn playlists_in_folder(&self, folder_id: u32) -> Vec<Playlist> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for playlist in reader.playlists.rows.values() {
+                if playlist.parent_id == folder_id {
+                    ret.push(playlist.clone());
+                }
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    /// Tracks of a playlist in playlist order.
+    pub fn title_by_playlist(&self, playlist_id: u32) -> Vec<Track> {
+        let mut titles = vec![];
+        self.read(&mut |reader| {
+            if let Some(playlist) = reader.playlists.rows.get(&playlist_id) {
+                for track_id in &playlist.track_ids {
+                    if let Some(track) = reader.tracks.rows.get(track_id) {
+                        titles.push(track.clone());
+                    }
+                }
+            }
+        });
+
+        titles
+    }
+
+    /// Tracks where the title, artist or album contains `query`, ignoring case,
+    /// accents and character width.
+    pub fn search(&self, query: &str) -> Vec<Track> {
+        let query = fold(query.trim());
+        let mut titles = vec![];
+        if query.is_empty() {
+            return titles;
+        }
+
+        self.read(&mut |reader| {
+            for track in reader.tracks.rows.values() {
+                let artist = reader.artists.rows.get(&track.artist_id).map(|artist| artist.name.as_str());
+                let album = reader.albums.rows.get(&track.album_id).map(|album| album.name.as_str());
+
+                let matches = [Some(track.title.as_str()), artist, album]
+                    .iter()
+                    .flatten()
+                    .any(|value| fold(value).contains(&query));
+
+                if matches {
+                    titles.push(track.clone());
+                }
+            }
+        });
+
+        sorted_by_name(titles)
+    }
+
+    pub fn record_play(&self, track_id: u32) -> Result<(), DatabaseError> {
+        let mut session_file = None;
+        self.write(|db| {
+            let path = match db.tracks.rows.get(&track_id) {
+                Some(track) => track.path.clone(),
+                None => return Err(DatabaseError::Unknown),
+            };
+
+            session_file = db.history.record(&path, std::time::SystemTime::now());
+            Ok(())
+        })?;
+
+        // Written once the lock is released, players keep browsing meanwhile.
+        if let Err(err) = session_file.map_or(Ok(()), |session_file| session_file.write()) {
+            eprintln!("Failed persisting play history; error = {}", err);
+        }
+
+        Ok(())
+    }
+
+    pub fn history_sessions(&self) -> Vec<HistorySession> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for (index, session) in reader.history.sessions().iter().enumerate() {
+                ret.push(HistorySession {
+                    id: index as u32 + 1,
+                    name: session.name.clone(),
+                });
+            }
+        });
+
+        ret
+    }
+
+    /// Tracks of a history session in played order, files that left the library are skipped.
+    pub fn title_by_history(&self, history_id: u32) -> Vec<Track> {
+        let mut titles = vec![];
+        self.read(&mut |reader| {
+            let session = match reader.history.sessions().get(history_id.wrapping_sub(1) as usize) {
+                Some(session) => session,
+                None => return,
+            };
+
+            let tracks_by_path = reader.tracks.rows.values()
+                .map(|track| (normalize_path(&track.path), track))
+                .collect::<HashMap<PathBuf, &Track>>();
+
+            for path in &session.paths {
+                if let Some(track) = tracks_by_path.get(&normalize_path(path)) {
+                    titles.push((*track).clone());
+                }
+            }
+        });
+
+        titles
+    }
+
     pub fn title_by_artist(&self, artist_id: u32) -> Vec<Track> {
         let mut titles: Vec<Track> = vec![];
         self.read(&mut |reader| {
@@ -255,28 +1475,38 @@ impl Database {
                 titles.push(track.clone());
             }
         });
-        titles
+        sorted_by_name(titles)
     }
 
-    fn index(&self, track: MetadataTrack) -> Result<(), DatabaseError> {
+    pub(super) fn index(&self, track: MetadataTrack) -> Result<(), DatabaseError> {
         self.write(|db| {
-            let artist_id = db.artists.insert(NewArtist {
-                name: track.metadata.artist,
-            });
-            db.tracks.insert(NewTrack {
-                artist_id,
-                path: track.path,
-                title: track.metadata.title,
-                size: track.size,
-                bpm: track.metadata.bpm,
-            });
+            let modified = std::fs::metadata(&track.path).and_then(|metadata| metadata.modified()).ok();
+            let previous = db.tracks.rows.values()
+                .find(|row| row.path == track.path)
+                .map(|row| row.id)
+                .and_then(|track_id| db.tracks.rows.remove(&track_id));
 
+            db.index_track(track, modified, previous);
             Ok(())
         })
     }
 
-    fn tracks(&self) -> Result<Vec<Track>, DatabaseError> {
-        Ok(vec![])
+    pub fn tracks(&self) -> Vec<Track> {
+        let mut ret = vec![];
+        self.read(&mut |reader| {
+            for track in reader.tracks.rows.values() {
+                ret.push(track.clone());
+            }
+        });
+
+        sorted_by_name(ret)
+    }
+
+    fn index_playlist(&self, playlist: PlaylistFile) -> Result<(), DatabaseError> {
+        self.write(|db| {
+            db.index_playlist(playlist);
+            Ok(())
+        })
     }
 
     fn read<T>(&self, closure: &mut T)
@@ -324,3 +1554,410 @@ fn it_can_insert_artists() {
     //table.insert(SomeModel);
     //assert_eq!(3, *table.sequence.counter.clone().lock().unwrap());
 }
+
+#[cfg(test)]
+fn track(artist: &str, album: &str, title: &str) -> MetadataTrack {
+    MetadataTrack::new(
+        crate::rekordbox::Metadata {
+            artist: artist.to_string(),
+            album: album.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        },
+        PathBuf::from(format!("/music/{}.mp3", title)),
+        0,
+    )
+}
+
+/// An in-memory library indexing the tracks added to it.
+#[cfg(test)]
+pub(super) struct TestLibrary(Vec<MetadataTrack>);
+
+#[cfg(test)]
+impl TestLibrary {
+    pub(super) fn new() -> Self {
+        TestLibrary(vec![])
+    }
+
+    pub(super) fn track(self, artist: &str, album: &str, title: &str) -> Self {
+        self.tagged(artist, album, title, |_metadata| {})
+    }
+
+    /// Add a track with further tags set by `tag`.
+    pub(super) fn tagged<F>(self, artist: &str, album: &str, title: &str, tag: F) -> Self
+        where F: FnOnce(&mut crate::rekordbox::Metadata)
+    {
+        let mut track = track(artist, album, title);
+        tag(&mut track.metadata);
+        self.file(track)
+    }
+
+    /// Add a track read from somewhere else than "/music/<title>.mp3".
+    pub(super) fn file(mut self, track: MetadataTrack) -> Self {
+        self.0.push(track);
+        self
+    }
+
+    pub(super) fn build(self) -> Database {
+        let database = Database::new("./test/does-not-exist");
+        for track in self.0 {
+            database.index(track).unwrap();
+        }
+        database
+    }
+}
+
+#[test]
+fn it_sorts_by_name_ignoring_case_accents_and_the() {
+    #[derive(Debug, PartialEq)]
+    struct Item(u32, String);
+    impl Record for Item {
+        fn name(&self) -> &String { &self.1 }
+        fn id(&self) -> &u32 { &self.0 }
+    }
+
+    let items = vec![
+        Item(1, "The Beatles".to_string()),
+        Item(2, "beta".to_string()),
+        Item(3, "Alpha".to_string()),
+        Item(4, "alpha".to_string()),
+        Item(5, "Étienne".to_string()),
+        Item(6, "Eagles".to_string()),
+        Item(7, "Theory".to_string()),
+    ];
+
+    assert_eq!(
+        vec![3, 4, 1, 2, 6, 5, 7],
+        sorted_by_name(items).iter().map(|item| *item.id()).collect::<Vec<u32>>(),
+    );
+}
+
+#[test]
+fn it_relates_albums_to_artists_and_tracks() {
+    let database = TestLibrary::new()
+        .track("Loopmasters", "Demo", "Demo Track 1")
+        .track("Loopmasters", "Demo", "Demo Track 2")
+        .track("Loopmasters", "", "Loose Track")
+        .track("Other Artist", "Demo", "Other Track")
+        .build();
+
+    let artist_id = *database.artists().iter()
+        .find(|artist| artist.name() == "Loopmasters")
+        .unwrap()
+        .id();
+
+    let albums = database.album_by_artist(artist_id);
+    assert_eq!(1, albums.len());
+    assert_eq!("Demo", albums[0].name());
+    assert_eq!(2, database.albums().len());
+    assert!(database.has_unknown_album(artist_id));
+
+    let album_id = *albums[0].id();
+    assert_eq!(2, database.title_by_album(album_id).len());
+    assert_eq!(2, database.title_by_artist_album(artist_id, album_id).len());
+
+    assert_eq!(4, database.tracks().len());
+
+    let unknown = database.title_by_artist_album(artist_id, UNKNOWN_ALBUM_ID);
+    assert_eq!(1, unknown.len());
+    assert_eq!("Loose Track", unknown[0].name());
+}
+
+#[test]
+fn it_relates_genres_to_artists_albums_and_tracks() {
+    let genre = |name: &str| {
+        let name = name.to_string();
+        move |metadata: &mut crate::rekordbox::Metadata| metadata.genre = name
+    };
+
+    let database = TestLibrary::new()
+        .tagged("Loopmasters", "Demo", "Demo Track 1", genre("Techno"))
+        .tagged("Loopmasters", "Demo", "Demo Track 2", genre("House"))
+        .tagged("Other Artist", "", "Other Track", genre("Techno"))
+        .track("Loopmasters", "Demo", "Untagged")
+        .build();
+
+    assert_eq!(2, database.genres().len());
+    let genre_id = *database.genres().iter()
+        .find(|genre| genre.name() == "Techno")
+        .unwrap()
+        .id();
+
+    let artists = database.artist_by_genre(genre_id);
+    assert_eq!(2, artists.len());
+
+    let artist_id = *artists.iter().find(|artist| artist.name() == "Loopmasters").unwrap().id();
+    let albums = database.album_by_genre_artist(genre_id, artist_id);
+    assert_eq!(1, albums.len());
+
+    let titles = database.title_by_genre_artist_album(genre_id, artist_id, *albums[0].id());
+    assert_eq!(1, titles.len());
+    assert_eq!("Demo Track 1", titles[0].name());
+}
+
+#[test]
+fn it_shares_artwork_between_tracks_with_the_same_cover() {
+    let cover = |data: &'static [u8]| move |metadata: &mut crate::rekordbox::Metadata| {
+        metadata.artwork = Some(crate::rekordbox::Artwork {
+            mime_type: "image/jpeg".to_string(),
+            data: Bytes::from_static(data),
+        });
+    };
+
+    let database = TestLibrary::new()
+        .tagged("Loopmasters", "Demo", "Demo Track 1", cover(b"demo cover"))
+        .tagged("Loopmasters", "Demo", "Demo Track 2", cover(b"demo cover"))
+        .tagged("Other Artist", "Other", "Other Track", cover(b"other cover"))
+        .track("Loopmasters", "", "Untagged")
+        .build();
+
+    let artwork_of = |title: &str| database.tracks().iter()
+        .find(|track| track.name() == title)
+        .unwrap()
+        .artwork_id;
+    let artwork_id = artwork_of("Demo Track 1");
+    assert_ne!(NO_ARTWORK_ID, artwork_id);
+    assert_eq!(artwork_id, artwork_of("Demo Track 2"));
+    assert_ne!(artwork_id, artwork_of("Other Track"));
+    assert_eq!(NO_ARTWORK_ID, artwork_of("Untagged"));
+    assert_eq!(Some(Bytes::from_static(b"demo cover")), database.artwork(artwork_id));
+}
+
+#[test]
+fn it_finds_tracks_by_key_distance() {
+    let database = [("A", "Am"), ("B", "8A"), ("C", "Em"), ("D", "C"), ("E", "F#m"), ("F", "")].iter()
+        .fold(TestLibrary::new(), |library, (title, key)| {
+            library.tagged("Loopmasters", "", title, |metadata| metadata.key = key.to_string())
+        })
+        .build();
+
+    assert_eq!(4, database.keys().len());
+    let key_id = *database.keys().iter()
+        .find(|key| key.name() == "Am")
+        .unwrap()
+        .id();
+
+    let titles = |distance| {
+        let mut titles = database.title_by_key_distance(key_id, distance)
+            .iter()
+            .map(|track| track.name().clone())
+            .collect::<Vec<String>>();
+        titles.sort();
+        titles
+    };
+
+    assert_eq!(vec!["A", "B"], titles(0));
+    assert_eq!(vec!["A", "B", "C", "D"], titles(1));
+    assert_eq!(vec!["A", "B", "C", "D", "E"], titles(3));
+}
+
+#[test]
+fn it_resolves_playlists_into_folders() {
+    let database = TestLibrary::new()
+        .track("Loopmasters", "", "Demo Track 1")
+        .track("Loopmasters", "", "Demo Track 2")
+        .build();
+
+    database.index_playlist(PlaylistFile {
+        folders: vec!["Sets".to_string(), "2020".to_string()],
+        name: "Warmup".to_string(),
+        entries: vec![
+            PathBuf::from("/music/Demo Track 2.mp3"),
+            PathBuf::from("/music/Missing.mp3"),
+            PathBuf::from("/music/Demo Track 1.mp3"),
+        ],
+    }).unwrap();
+    database.index_playlist(PlaylistFile {
+        folders: vec!["Sets".to_string()],
+        name: "Closing".to_string(),
+        entries: vec![],
+    }).unwrap();
+
+    assert_eq!(2, database.playlist_count());
+
+    let root = database.playlists_in_folder(ROOT_PLAYLIST_FOLDER_ID);
+    assert_eq!(1, root.len());
+    assert!(root[0].is_folder);
+
+    let sets = database.playlists_in_folder(*root[0].id());
+    assert_eq!(2, sets.len());
+
+    let year = sets.iter().find(|playlist| playlist.is_folder).unwrap();
+    let warmup = &database.playlists_in_folder(*year.id())[0];
+    assert_eq!("Warmup", warmup.name());
+
+    let titles = database.title_by_playlist(*warmup.id())
+        .iter()
+        .map(|track| track.name().clone())
+        .collect::<Vec<String>>();
+    assert_eq!(vec!["Demo Track 2", "Demo Track 1"], titles);
+}
+
+#[test]
+fn it_searches_titles_artists_and_albums() {
+    let database = TestLibrary::new()
+        .track("Beyoncé", "", "Halo")
+        .track("Röyksopp", "Melody A.M.", "Eple")
+        .track("Loopmasters", "", "Demo Track 1")
+        .build();
+
+    let titles = |query| {
+        let mut titles = database.search(query)
+            .iter()
+            .map(|track| track.name().clone())
+            .collect::<Vec<String>>();
+        titles.sort();
+        titles
+    };
+
+    assert_eq!(vec!["Halo"], titles("BEYONCE"));
+    assert_eq!(vec!["Eple"], titles("melody"));
+    assert_eq!(vec!["Demo Track 1"], titles("ＤＥＭＯ"));
+    assert_eq!(vec!["Demo Track 1", "Eple", "Halo"], titles("É"));
+    assert!(titles("  ").is_empty());
+}
+
+#[test]
+fn it_records_played_tracks_in_the_history() {
+    let database = TestLibrary::new()
+        .track("Loopmasters", "", "Demo Track 1")
+        .track("Loopmasters", "", "Demo Track 2")
+        .build()
+        .with_history(History::load(None));
+
+    let track_id = |title: &str| {
+        *database.tracks().iter().find(|track| track.name() == title).unwrap().id()
+    };
+
+    database.record_play(track_id("Demo Track 2")).unwrap();
+    database.record_play(track_id("Demo Track 1")).unwrap();
+    assert!(database.record_play(1234).is_err());
+
+    let sessions = database.history_sessions();
+    assert_eq!(1, sessions.len());
+
+    let titles = database.title_by_history(*sessions[0].id())
+        .iter()
+        .map(|track| track.name().clone())
+        .collect::<Vec<String>>();
+    assert_eq!(vec!["Demo Track 2", "Demo Track 1"], titles);
+}
+
+#[cfg(test)]
+fn write_tagged_mp3(path: &Path, artist: &str, title: &str) {
+    let mut tag = id3::Tag::new();
+    tag.set_artist(artist);
+    tag.set_title(title);
+
+    let mut bytes = vec![];
+    tag.write_to(&mut bytes, id3::Version::Id3v24).unwrap();
+    std::fs::write(path, bytes).unwrap();
+}
+
+#[test]
+fn it_keeps_ids_stable_and_skips_unchanged_files_between_runs() {
+    let root = std::env::temp_dir().join(format!("termdj-library-{}", std::process::id()));
+    let store = root.with_extension("db");
+    std::fs::create_dir_all(&root).unwrap();
+    write_tagged_mp3(&root.join("a.mp3"), "Loopmasters", "Demo Track 1");
+    write_tagged_mp3(&root.join("b.mp3"), "Loopmasters", "Demo Track 2");
+
+    let track_id = |database: &Database, title: &str| {
+        database.tracks().iter().find(|track| track.name() == title).map(|track| *track.id())
+    };
+
+    let database = Database::open(vec![LibraryRoot::new(&root)], &ScanOptions::default(), Some(store.clone()));
+    let first_id = track_id(&database, "Demo Track 1").unwrap();
+    let second_id = track_id(&database, "Demo Track 2").unwrap();
+    drop(database);
+
+    // Same size and modification time, the stored tags are trusted.
+    let modified = std::fs::metadata(root.join("b.mp3")).unwrap().modified().unwrap();
+    write_tagged_mp3(&root.join("b.mp3"), "Loopmasters", "Demo Track X");
+    std::fs::File::options().write(true).open(root.join("b.mp3")).unwrap().set_modified(modified).unwrap();
+
+    std::fs::remove_file(root.join("a.mp3")).unwrap();
+    write_tagged_mp3(&root.join("c.mp3"), "Other Artist", "Demo Track 3");
+
+    let database = Database::open(vec![LibraryRoot::new(&root)], &ScanOptions::default(), Some(store.clone()));
+    std::fs::remove_dir_all(&root).unwrap();
+    std::fs::remove_file(&store).unwrap();
+
+    assert_eq!(Some(second_id), track_id(&database, "Demo Track 2"));
+    assert_eq!(None, track_id(&database, "Demo Track 1"));
+    // Ids of removed tracks aren't handed out again.
+    assert!(track_id(&database, "Demo Track 3").unwrap() > first_id.max(second_id));
+    assert_eq!(
+        vec!["Loopmasters", "Other Artist"],
+        database.artists().iter().map(|artist| artist.name().clone()).collect::<Vec<String>>(),
+    );
+}
+
+#[test]
+fn it_reads_every_file_again_when_the_scan_options_change() {
+    let root = std::env::temp_dir().join(format!("termdj-options-{}", std::process::id()));
+    let store = root.with_extension("db");
+    std::fs::create_dir_all(&root).unwrap();
+    write_tagged_mp3(&root.join("a.mp3"), "Loopmasters", "Demo Track 1");
+
+    let titles = |database: &Database| {
+        database.tracks().iter().map(|track| track.name().clone()).collect::<Vec<String>>()
+    };
+
+    let database = Database::open(vec![LibraryRoot::new(&root)], &ScanOptions::default(), Some(store.clone()));
+    let track_id = *database.tracks()[0].id();
+    drop(database);
+
+    let modified = std::fs::metadata(root.join("a.mp3")).unwrap().modified().unwrap();
+    write_tagged_mp3(&root.join("a.mp3"), "Loopmasters", "Demo Track X");
+    std::fs::File::options().write(true).open(root.join("a.mp3")).unwrap().set_modified(modified).unwrap();
+
+    let unchanged = titles(&Database::open(vec![LibraryRoot::new(&root)], &ScanOptions::default(), Some(store.clone())));
+    let detecting = ScanOptions { detect_keys: true };
+    let database = Database::open(vec![LibraryRoot::new(&root)], &detecting, Some(store.clone()));
+    std::fs::remove_dir_all(&root).unwrap();
+    std::fs::remove_file(&store).unwrap();
+
+    assert_eq!(vec!["Demo Track 1"], unchanged);
+    assert_eq!(vec!["Demo Track X"], titles(&database));
+    assert_eq!(track_id, *database.tracks()[0].id());
+}
+
+#[test]
+fn it_shows_paths_that_are_not_utf8() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut latin1 = track("Loopmasters", "", "Caf\u{e9}");
+    latin1.path = PathBuf::from(std::ffi::OsStr::from_bytes(b"/music/Caf\xe9.mp3"));
+    let database = TestLibrary::new().file(latin1).build();
+
+    assert_eq!("/music/Caf\u{fffd}.mp3", database.tracks()[0].path());
+}
+
+#[test]
+fn it_updates_only_the_paths_that_changed() {
+    let root = std::env::temp_dir().join(format!("termdj-update-{}", std::process::id()));
+    std::fs::create_dir_all(root.join("album")).unwrap();
+    write_tagged_mp3(&root.join("a.mp3"), "Loopmasters", "Demo Track 1");
+    write_tagged_mp3(&root.join("b.mp3"), "Loopmasters", "Demo Track 2");
+    write_tagged_mp3(&root.join("album/c.mp3"), "Loopmasters", "Demo Track 3");
+
+    let titles = |database: &Database| {
+        database.tracks().iter().map(|track| track.name().clone()).collect::<Vec<String>>()
+    };
+
+    let database = Database::new(&root);
+    let track_id = |title: &str| *database.tracks().iter().find(|track| track.name() == title).unwrap().id();
+    let edited_id = track_id("Demo Track 1");
+
+    write_tagged_mp3(&root.join("a.mp3"), "Loopmasters", "Demo Track 1 (Edit)");
+    // Not among the updated paths, so not read again.
+    write_tagged_mp3(&root.join("b.mp3"), "Loopmasters", "Demo Track 2 (Edit)");
+    std::fs::remove_dir_all(root.join("album")).unwrap();
+    write_tagged_mp3(&root.join("d.mp3"), "Loopmasters", "Demo Track 4");
+    database.update(&[root.join("a.mp3"), root.join("album"), root.join("d.mp3")]).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(vec!["Demo Track 1 (Edit)", "Demo Track 2", "Demo Track 4"], titles(&database));
+    assert_eq!(edited_id, track_id("Demo Track 1 (Edit)"));
+}
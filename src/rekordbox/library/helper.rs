@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::rekordbox::{Artist};
+use super::database::UNKNOWN_ALBUM_ID;
 
 type Database = Arc<crate::rekordbox::library::Database>;
 
@@ -8,6 +9,80 @@ pub fn number_of_artists(database: &Database) -> u32 {
     database.artists().len() as u32
 }
 
+pub fn number_of_tracks(database: &Database) -> u32 {
+    database.tracks().len() as u32
+}
+
+pub fn number_of_albums(database: &Database) -> u32 {
+    database.albums().len() as u32
+}
+
+/// Albums of the artist, including the "Unknown" album when there are tracks without one.
+pub fn number_of_albums_by_artist(artist_id: u32, database: &Database) -> u32 {
+    database.album_by_artist(artist_id).len() as u32
+        + database.has_unknown_album(artist_id) as u32
+}
+
+pub fn number_of_tracks_by_album(album_id: u32, database: &Database) -> u32 {
+    database.title_by_album(album_id).len() as u32
+}
+
+pub fn number_of_tracks_by_artist_album(artist_id: u32, album_id: u32, database: &Database) -> u32 {
+    database.title_by_artist_album(artist_id, album_id).len() as u32
+}
+
+pub fn number_of_genres(database: &Database) -> u32 {
+    database.genres().len() as u32
+}
+
+pub fn number_of_artists_by_genre(genre_id: u32, database: &Database) -> u32 {
+    database.artist_by_genre(genre_id).len() as u32
+}
+
+/// Albums of the artist within a genre, including the "Unknown" album when needed.
+pub fn number_of_albums_by_genre_artist(genre_id: u32, artist_id: u32, database: &Database) -> u32 {
+    database.album_by_genre_artist(genre_id, artist_id).len() as u32
+        + has_unknown_album_by_genre_artist(genre_id, artist_id, database) as u32
+}
+
+pub fn has_unknown_album_by_genre_artist(genre_id: u32, artist_id: u32, database: &Database) -> bool {
+    !database.title_by_genre_artist_album(genre_id, artist_id, UNKNOWN_ALBUM_ID).is_empty()
+}
+
+pub fn number_of_tracks_by_genre_artist_album(
+    genre_id: u32,
+    artist_id: u32,
+    album_id: u32,
+    database: &Database,
+) -> u32 {
+    database.title_by_genre_artist_album(genre_id, artist_id, album_id).len() as u32
+}
+
+pub fn number_of_keys(database: &Database) -> u32 {
+    database.keys().len() as u32
+}
+
+pub fn number_of_tracks_by_key_distance(key_id: u32, distance: u32, database: &Database) -> u32 {
+    database.title_by_key_distance(key_id, distance).len() as u32
+}
+
+/// Children of a folder, or tracks of a playlist.
+pub fn number_of_playlist_items(id: u32, is_folder: bool, database: &Database) -> u32 {
+    if is_folder {
+        database.playlists_in_folder(id).len() as u32
+    } else {
+        database.title_by_playlist(id).len() as u32
+    }
+}
+
+pub fn number_of_history_sessions(database: &Database) -> u32 {
+    database.history_sessions().len() as u32
+}
+
+pub fn number_of_tracks_by_history(history_id: u32, database: &Database) -> u32 {
+    database.title_by_history(history_id).len() as u32
+}
+
 pub fn number_of_tracks_by_artist(artist_id: u32, database: &Database) -> u32 {
     database.title_by_artist(artist_id).len() as u32
 }
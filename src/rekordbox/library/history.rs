@@ -0,0 +1,189 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::library::read_playlist;
+use crate::utils::data_folder;
+
+/// Plays closer together than this belong to the same set, even across midnight.
+const SESSION_GAP: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Tracks played during one set, in played order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Session {
+    pub name: String,
+    pub paths: Vec<PathBuf>,
+}
+
+/// The current session as it's to be written to disk, done by the caller so
+/// the file isn't written while the library is locked.
+#[derive(Debug, PartialEq)]
+pub struct SessionFile {
+    file: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl SessionFile {
+    pub fn write(&self) -> std::io::Result<()> {
+        if let Some(folder) = self.file.parent() {
+            fs::create_dir_all(folder)?;
+        }
+
+        let mut file = fs::File::create(&self.file)?;
+        writeln!(file, "#EXTM3U")?;
+        for path in &self.paths {
+            writeln!(file, "{}", path.display())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Log of what was played on the players, persisted as one m3u8 playlist per
+/// session so the sets can be opened in other software as well.
+#[derive(Debug, Default)]
+pub struct History {
+    folder: Option<PathBuf>,
+    sessions: Vec<Session>,
+    last_play: Option<SystemTime>,
+}
+
+pub fn default_history_folder() -> Option<PathBuf> {
+    Some(data_folder()?.join("history"))
+}
+
+/// ISO 8601 date (UTC) of a point in time, e.g. "2020-04-23".
+fn date(time: SystemTime) -> String {
+    let days = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64 / 86400;
+
+    // Civil date from days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+impl History {
+    /// Read previously recorded sessions, oldest first.
+    pub fn load(folder: Option<PathBuf>) -> Self {
+        let mut sessions = vec![];
+
+        if let Some(entries) = folder.as_ref().and_then(|folder| fs::read_dir(folder).ok()) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let path = entry.path();
+                if path.extension().and_then(|extension| extension.to_str()) != Some("m3u8") {
+                    continue;
+                }
+
+                if let (Some(name), Some(paths)) = (path.file_stem(), read_playlist(&path)) {
+                    sessions.push(Session {
+                        name: name.to_string_lossy().to_string(),
+                        paths,
+                    });
+                }
+            }
+        }
+
+        // Session names start with their date, so they sort chronologically.
+        sessions.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            folder,
+            sessions,
+            last_play: None,
+        }
+    }
+
+    pub fn sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+
+    /// Append a played track to the current session, starting a new session
+    /// when nothing was played for a while. Returns the session to write when
+    /// the history is persisted.
+    pub fn record(&mut self, path: &Path, time: SystemTime) -> Option<SessionFile> {
+        let continues_session = self.last_play
+            .and_then(|last_play| time.duration_since(last_play).ok())
+            .map(|elapsed| elapsed < SESSION_GAP)
+            .unwrap_or(false);
+
+        if !continues_session || self.sessions.is_empty() {
+            let name = date(time);
+            // Played again later on a day that already has a set, e.g. after a restart.
+            match self.sessions.last() {
+                Some(session) if session.name == name => {},
+                _ => self.sessions.push(Session { name, paths: vec![] }),
+            }
+        }
+
+        self.last_play = Some(time);
+        if let Some(session) = self.sessions.last_mut() {
+            session.paths.push(path.to_path_buf());
+        }
+
+        let (folder, session) = (self.folder.as_ref()?, self.sessions.last()?);
+        Some(SessionFile {
+            file: folder.join(format!("{}.m3u8", session.name)),
+            paths: session.paths.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn it_formats_dates() {
+        assert_eq!("1970-01-01", date(at(0)));
+        assert_eq!("2020-04-23", date(at(1587600000)));
+        assert_eq!("2000-02-29", date(at(951782400)));
+    }
+
+    #[test]
+    fn it_groups_plays_into_sessions() {
+        let mut history = History::load(None);
+        // 2020-04-23 23:30, a set running past midnight
+        history.record(Path::new("/music/a.mp3"), at(1587684600));
+        history.record(Path::new("/music/b.mp3"), at(1587684600 + 3600));
+        // The next evening
+        history.record(Path::new("/music/c.mp3"), at(1587684600 + 20 * 3600));
+
+        assert_eq!(vec![
+            Session {
+                name: "2020-04-23".to_string(),
+                paths: vec![PathBuf::from("/music/a.mp3"), PathBuf::from("/music/b.mp3")],
+            },
+            Session {
+                name: "2020-04-24".to_string(),
+                paths: vec![PathBuf::from("/music/c.mp3")],
+            },
+        ], history.sessions());
+    }
+
+    #[test]
+    fn it_persists_sessions() {
+        let folder = std::env::temp_dir().join(format!("termdj-history-{}", std::process::id()));
+
+        let mut history = History::load(Some(folder.clone()));
+        history.record(Path::new("/music/a.mp3"), at(1587600000)).unwrap().write().unwrap();
+        history.record(Path::new("/music/b.mp3"), at(1587600300)).unwrap().write().unwrap();
+
+        let loaded = History::load(Some(folder.clone()));
+        fs::remove_dir_all(&folder).unwrap();
+
+        assert_eq!(history.sessions(), loaded.sessions());
+    }
+}
@@ -12,10 +12,21 @@ pub const DURATION: MetadataType = 0x0000000b;
 pub const BPM: MetadataType = 0x0000000d;
 pub const LABEL: MetadataType = 0x0000000e;
 pub const KEY: MetadataType = 0x0000000f;
+pub const YEAR: MetadataType = 0x00000011;
 pub const COLOR_NONE: MetadataType = 0x00000013;
+pub const COLOR_PINK: MetadataType = 0x00000014;
+pub const COLOR_RED: MetadataType = 0x00000015;
+pub const COLOR_ORANGE: MetadataType = 0x00000016;
+pub const COLOR_YELLOW: MetadataType = 0x00000017;
+pub const COLOR_GREEN: MetadataType = 0x00000018;
+pub const COLOR_AQUA: MetadataType = 0x00000019;
+pub const COLOR_BLUE: MetadataType = 0x0000001a;
+pub const COLOR_PURPLE: MetadataType = 0x0000001b;
 pub const UNKNOWN1: MetadataType = 0x0000002f;
 
 pub const COMMENT: MetadataType = 0x00000023;
+pub const HISTORY_PLAYLIST: MetadataType = 0x00000024;
+pub const ROOT_GENRE: MetadataType = 0x00000080;
 pub const ROOT_ARTIST: MetadataType = 0x00000081;
 pub const ROOT_ALBUM: MetadataType = 0x00000082;
 pub const ROOT_TRACK: MetadataType = 0x00000083;
@@ -1,11 +1,95 @@
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug)]
+use super::metadata_type::{self, MetadataType};
+
+/// Track colors as they are presented in the player browser.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub enum Color {
+    Pink,
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Aqua,
+    Blue,
+    Purple,
+}
+
+impl Color {
+    pub fn from_name(name: &str) -> Option<Color> {
+        Some(match name.trim().to_lowercase().as_str() {
+            "pink" => Color::Pink,
+            "red" => Color::Red,
+            "orange" => Color::Orange,
+            "yellow" => Color::Yellow,
+            "green" => Color::Green,
+            "aqua" => Color::Aqua,
+            "blue" => Color::Blue,
+            "purple" => Color::Purple,
+            _ => return None,
+        })
+    }
+
+    pub fn metadata_type(color: Option<Color>) -> MetadataType {
+        match color {
+            None => metadata_type::COLOR_NONE,
+            Some(Color::Pink) => metadata_type::COLOR_PINK,
+            Some(Color::Red) => metadata_type::COLOR_RED,
+            Some(Color::Orange) => metadata_type::COLOR_ORANGE,
+            Some(Color::Yellow) => metadata_type::COLOR_YELLOW,
+            Some(Color::Green) => metadata_type::COLOR_GREEN,
+            Some(Color::Aqua) => metadata_type::COLOR_AQUA,
+            Some(Color::Blue) => metadata_type::COLOR_BLUE,
+            Some(Color::Purple) => metadata_type::COLOR_PURPLE,
+        }
+    }
+}
+
+/// Hot cues are numbered from A (0) to H (7).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub enum CueKind {
+    Memory,
+    HotCue(u8),
+}
+
+/// A cue point or loop, times in milliseconds since the start of the track.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Cue {
+    pub kind: CueKind,
+    pub start: u32,
+    /// End of the loop, `None` for plain cue points.
+    pub end: Option<u32>,
+    pub color: Option<[u8; 3]>,
+    pub comment: String,
+}
+
+/// Cover image of a track, embedded in the audio file or kept next to it.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Artwork {
+    pub mime_type: String,
+    pub data: Bytes,
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct Metadata {
     pub artist: String,
     pub title: String,
     pub bpm: Option<u32>,
     pub album: String,
+    /// Playing time in whole seconds
+    pub duration: Option<u32>,
+    pub genre: String,
+    pub key: String,
+    pub label: String,
+    pub year: Option<u32>,
+    pub comment: String,
+    /// Rating in stars, 0 to 5
+    pub rating: Option<u8>,
+    pub color: Option<Color>,
+    pub artwork: Option<Artwork>,
+    pub cues: Vec<Cue>,
 }
 
 #[derive(Debug)]
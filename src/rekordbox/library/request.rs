@@ -1,9 +1,60 @@
 use bytes::{Bytes, BytesMut};
+use std::fmt;
+
 use crate::rekordbox::DBMessage;
+use crate::rekordbox::db_field::DBField;
+use crate::rekordbox::db_message_argument::ArgumentCollection;
+use crate::rekordbox::db_request_type::DBRequestType;
 use crate::rekordbox::library::ClientState;
 
+/// Why a request can't be answered, the player is told the data is
+/// unavailable instead.
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    /// The request has fewer arguments than its type takes.
+    MissingArgument(usize),
+    /// The argument at this index isn't a number.
+    InvalidArgument(usize),
+    UnknownTrack(u32),
+    UnknownArtist(u32),
+    /// The track is still being analysed.
+    AnalysisPending(u32),
+    /// A render request that doesn't follow a menu request.
+    NothingToRender,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RequestError::MissingArgument(index) => write!(f, "Missing argument {}", index),
+            RequestError::InvalidArgument(index) => write!(f, "Argument {} is not a number", index),
+            RequestError::UnknownTrack(id) => write!(f, "No track with id {}", id),
+            RequestError::UnknownArtist(id) => write!(f, "No artist with id {}", id),
+            RequestError::AnalysisPending(id) => write!(f, "Track {} is still being analysed", id),
+            RequestError::NothingToRender => write!(f, "No menu requested before rendering"),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
 pub trait Controller {
-    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Bytes;
+    fn to_response(&self, request: RequestWrapper, context: &mut ClientState) -> Result<Bytes, RequestError>;
+}
+
+/// The failure response, telling the player the data it asked for with a
+/// request of `request_type` isn't available.
+pub fn unavailable_response(transaction_id: DBField, request_type: DBRequestType) -> Bytes {
+    let request_type_value = request_type.value();
+
+    Bytes::from(DBMessage::new(
+        transaction_id,
+        DBRequestType::Unavailable,
+        ArgumentCollection::new(vec![
+            DBField::from([0x00, 0x00, request_type_value[0], request_type_value[1]]),
+            DBField::from(0u32),
+        ]),
+    ))
 }
 
 pub struct RequestWrapper {
@@ -40,6 +91,15 @@ impl <'a>RequestHandler<'a> {
     }
 
     pub fn respond_to(self) -> Bytes {
-        self.controller.to_response(self.request, self.context)
+        let transaction_id = self.request.message.transaction_id.clone();
+        let request_type = self.request.message.request_type;
+
+        match self.controller.to_response(self.request, self.context) {
+            Ok(response) => response,
+            Err(err) => {
+                eprintln!("Failed answering {:?}; error = {}", request_type, err);
+                unavailable_response(transaction_id, request_type)
+            },
+        }
     }
 }
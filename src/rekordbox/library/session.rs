@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use super::sort_order::SortOrder;
+use super::StatefulRequest;
+use crate::rekordbox::db_field::DBFieldType;
+use crate::rekordbox::DBMessage;
+
+/// Where a request comes from: the player sending it and the menu on that
+/// player, the first two bytes of the DMST argument requests start with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct MenuLocation {
+    pub(super) player: u8,
+    pub(super) menu: u8,
+}
+
+impl MenuLocation {
+    pub(super) fn of(message: &DBMessage) -> MenuLocation {
+        match message.arguments.get(0) {
+            Some(dmst) if dmst.kind == DBFieldType::U32 && dmst.value.len() == 4 => MenuLocation {
+                player: dmst.value[0],
+                menu: dmst.value[1],
+            },
+            _ => MenuLocation::default(),
+        }
+    }
+}
+
+/// The menu a location last asked for, rendered by the next render request
+/// from there, and the order it was asked in.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct MenuSession {
+    pub(super) request: Option<StatefulRequest>,
+    pub(super) sort_order: SortOrder,
+}
+
+impl Default for MenuSession {
+    fn default() -> Self {
+        Self {
+            request: None,
+            sort_order: SortOrder::Default,
+        }
+    }
+}
+
+/// Sessions of every menu on every player, shared by all connections since a
+/// player can browse over more than one.
+#[derive(Debug, Default, Clone)]
+pub(super) struct MenuSessions(Arc<Mutex<HashMap<MenuLocation, MenuSession>>>);
+
+impl MenuSessions {
+    pub(super) fn get(&self, location: MenuLocation) -> MenuSession {
+        match self.0.lock() {
+            Ok(sessions) => sessions.get(&location).cloned().unwrap_or_default(),
+            Err(_) => MenuSession::default(),
+        }
+    }
+
+    pub(super) fn update<F: FnOnce(&mut MenuSession)>(&self, location: MenuLocation, update: F) {
+        if let Ok(mut sessions) = self.0.lock() {
+            update(sessions.entry(location).or_default());
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use super::database::{Database, Track};
+use crate::rekordbox::Record;
+use crate::utils::text::sort_key;
+
+/// Order of a track list as chosen in the sort menu of the player, sent as
+/// the second argument of every menu request.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum SortOrder {
+    /// Keep the natural order of the list, by name or e.g. playlist order.
+    Default,
+    Name,
+    Bpm,
+    Rating,
+    Key,
+    DateAdded,
+}
+
+impl SortOrder {
+    pub fn new(value: u32) -> SortOrder {
+        match value {
+            1 => SortOrder::Name,
+            4 => SortOrder::Bpm,
+            5 => SortOrder::Rating,
+            12 => SortOrder::Key,
+            17 => SortOrder::DateAdded,
+            _ => SortOrder::Default,
+        }
+    }
+
+    /// Sort `tracks`, ties and tracks missing the sorted field fall back to
+    /// the name so the order is always the same.
+    pub fn sort(&self, mut tracks: Vec<Track>, database: &Database) -> Vec<Track> {
+        match self {
+            SortOrder::Default => {},
+            SortOrder::Name => {
+                tracks.sort_by_cached_key(|track| (sort_key(track.name()), *track.id()));
+            },
+            SortOrder::Bpm => {
+                tracks.sort_by_cached_key(|track| {
+                    (track.bpm.is_none(), track.bpm, sort_key(track.name()), *track.id())
+                });
+            },
+            SortOrder::Rating => {
+                tracks.sort_by_cached_key(|track| {
+                    (Reverse(track.rating.unwrap_or(0)), sort_key(track.name()), *track.id())
+                });
+            },
+            SortOrder::Key => {
+                // Around the Camelot wheel like the KEY menu, 1A, 1B, 2A, ...
+                let positions = database.keys()
+                    .iter()
+                    .map(|key| (*key.id(), (key.key().camelot_number(), !key.key().is_minor())))
+                    .collect::<HashMap<u32, (u8, bool)>>();
+
+                tracks.sort_by_cached_key(|track| {
+                    let position = positions.get(&track.key_id);
+                    (position.is_none(), position.copied(), sort_key(track.name()), *track.id())
+                });
+            },
+            SortOrder::DateAdded => {
+                // Newest first, where the fresh tracks are looked for.
+                tracks.sort_by_cached_key(|track| {
+                    (track.date_added.is_none(), Reverse(track.date_added), sort_key(track.name()), *track.id())
+                });
+            },
+        }
+
+        tracks
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::super::database::TestLibrary;
+    use crate::rekordbox::Metadata;
+
+    fn titles(tracks: Vec<Track>) -> Vec<String> {
+        tracks.iter().map(|track| track.name().clone()).collect()
+    }
+
+    #[test]
+    fn it_sorts_tracks_by_bpm_and_rating() {
+        let rated = |bpm: Option<u32>, rating: Option<u8>| move |metadata: &mut Metadata| {
+            metadata.bpm = bpm;
+            metadata.rating = rating;
+        };
+
+        let database = TestLibrary::new()
+            .tagged("Loopmasters", "", "Fast", rated(Some(140), Some(1)))
+            .tagged("Loopmasters", "", "Slow", rated(Some(90), Some(5)))
+            .tagged("Loopmasters", "", "Unknown", rated(None, None))
+            .build();
+        let tracks = database.tracks();
+
+        assert_eq!(vec!["Slow", "Fast", "Unknown"], titles(SortOrder::Bpm.sort(tracks.clone(), &database)));
+        assert_eq!(vec!["Slow", "Fast", "Unknown"], titles(SortOrder::Rating.sort(tracks.clone(), &database)));
+        assert_eq!(vec!["Fast", "Slow", "Unknown"], titles(SortOrder::Name.sort(tracks, &database)));
+    }
+}
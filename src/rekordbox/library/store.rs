@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::utils::data_folder;
+
+/// Bumped whenever the layout of the stored library changes, files written by
+/// other versions are ignored and the library is scanned from scratch.
+const STORE_VERSION: u32 = 5;
+
+pub fn default_database_file() -> Option<PathBuf> {
+    Some(data_folder()?.join("library.db"))
+}
+
+/// Read a stored library, `None` when there is none yet or it was written by another version.
+pub fn load<S: for<'de> Deserialize<'de>>(file: &Path) -> Option<S> {
+    load_version(file, STORE_VERSION)
+}
+
+/// Write through a temporary file, a crash while saving leaves the previous
+/// database intact.
+pub fn save<S: Serialize>(file: &Path, snapshot: &S) -> io::Result<()> {
+    save_version(file, STORE_VERSION, snapshot)
+}
+
+/// Read a file written by `save_version`, `None` unless it was written with `version`.
+pub fn load_version<S: for<'de> Deserialize<'de>>(file: &Path, version: u32) -> Option<S> {
+    let bytes = fs::read(file).ok()?;
+    let mut reader = &bytes[..];
+
+    match bincode::deserialize_from::<_, u32>(&mut reader) {
+        Ok(stored) if stored == version => {},
+        _ => return None,
+    }
+
+    match bincode::deserialize_from(&mut reader) {
+        Ok(snapshot) => Some(snapshot),
+        Err(err) => {
+            eprintln!("Ignoring unreadable {}; error = {}", file.display(), err);
+            None
+        },
+    }
+}
+
+pub fn save_version<S: Serialize>(file: &Path, version: u32, snapshot: &S) -> io::Result<()> {
+    let bytes = bincode::serialize(&(version, snapshot)).map_err(io::Error::other)?;
+
+    if let Some(folder) = file.parent() {
+        fs::create_dir_all(folder)?;
+    }
+
+    let temporary = file.with_extension("tmp");
+    fs::write(&temporary, bytes)?;
+    fs::rename(&temporary, file)
+}
@@ -0,0 +1,136 @@
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::database::Database;
+
+/// Copying an album produces a burst of events per file, the library is
+/// updated once things have been quiet for this long.
+const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+fn is_hidden(path: &Path) -> bool {
+    path.components().any(|component| {
+        component.as_os_str().to_str().map(|name| name.starts_with('.')).unwrap_or(false)
+    })
+}
+
+/// Whether an event may change what the library holds, reading files or
+/// touching their permissions doesn't.
+fn is_library_change(event: &Event) -> bool {
+    let relevant_kind = match event.kind {
+        EventKind::Create(_) | EventKind::Remove(_) => true,
+        EventKind::Modify(ModifyKind::Metadata(_)) => false,
+        EventKind::Modify(_) => true,
+        _ => false,
+    };
+
+    relevant_kind && event.paths.iter().any(|path| !is_hidden(path))
+}
+
+/// Paths touched by a settled burst of events.
+#[derive(Debug, Default)]
+struct Batch {
+    paths: HashSet<PathBuf>,
+    /// Notify lost track of events, only a full rescan catches up.
+    rescan: bool,
+}
+
+impl Batch {
+    /// Take in `event`, telling whether it changes the library.
+    fn add(&mut self, event: notify::Result<Event>) -> bool {
+        match event {
+            Ok(event) if event.need_rescan() => self.rescan = true,
+            Ok(event) if is_library_change(&event) => {
+                self.paths.extend(event.paths.into_iter().filter(|path| !is_hidden(path)));
+            },
+            Ok(_) => return false,
+            Err(err) => {
+                eprintln!("Failed watching library; error = {}", err);
+                return false;
+            },
+        }
+
+        true
+    }
+}
+
+/// Keep `database` in line with its root folders while running, so tracks
+/// added or removed mid set show up on the players.
+pub fn watch(database: Arc<Database>) -> notify::Result<()> {
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for root in database.roots() {
+        watcher.watch(&root.path, RecursiveMode::Recursive)?;
+    }
+
+    while let Ok(event) = rx.recv() {
+        let mut batch = Batch::default();
+        if !batch.add(event) {
+            continue;
+        }
+
+        loop {
+            match rx.recv_timeout(SETTLE_TIME) {
+                Ok(event) => {
+                    batch.add(event);
+                },
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+
+        let result = if batch.rescan {
+            database.rescan()
+        } else {
+            database.update(&batch.paths.into_iter().collect::<Vec<PathBuf>>())
+        };
+
+        if let Err(err) = result {
+            eprintln!("Failed updating library; error = {:?}", err);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, Flag, MetadataKind, RemoveKind, RenameMode};
+
+    fn event(kind: EventKind, path: &str) -> Event {
+        Event::new(kind).add_path(PathBuf::from(path))
+    }
+
+    #[test]
+    fn it_only_updates_for_changes_to_the_library() {
+        assert!(is_library_change(&event(EventKind::Create(CreateKind::File), "/music/a.mp3")));
+        assert!(is_library_change(&event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), "/music/a.mp3")));
+        assert!(!is_library_change(&event(EventKind::Access(AccessKind::Any), "/music/a.mp3")));
+        assert!(!is_library_change(&event(EventKind::Modify(ModifyKind::Metadata(MetadataKind::Any)), "/music/a.mp3")));
+        assert!(!is_library_change(&event(EventKind::Create(CreateKind::File), "/music/.sync/a.mp3")));
+    }
+
+    #[test]
+    fn it_collects_the_paths_of_a_batch() {
+        let mut batch = Batch::default();
+        assert!(batch.add(Ok(event(EventKind::Create(CreateKind::File), "/music/a.mp3"))));
+        assert!(batch.add(Ok(event(EventKind::Modify(ModifyKind::Name(RenameMode::Both)), "/music/b.mp3")
+            .add_path(PathBuf::from("/music/c.mp3")))));
+        assert!(batch.add(Ok(event(EventKind::Remove(RemoveKind::File), "/music/a.mp3"))));
+        assert!(!batch.add(Ok(event(EventKind::Access(AccessKind::Any), "/music/d.mp3"))));
+
+        let expected = ["/music/a.mp3", "/music/b.mp3", "/music/c.mp3"].iter()
+            .map(PathBuf::from)
+            .collect::<HashSet<PathBuf>>();
+        assert_eq!(expected, batch.paths);
+        assert!(!batch.rescan);
+
+        assert!(batch.add(Ok(Event::new(EventKind::Other).set_flag(Flag::Rescan))));
+        assert!(batch.rescan);
+    }
+}
@@ -33,11 +33,14 @@ pub trait EventHandler<T> {
 }
 
 use status_event_server::StatusEventServer;
-pub use server::{Server, ServerState};
+pub use server::{DeviceOptions, Server, ServerState};
 pub use server::ApplicationEvent as Event;
 use rpc::server as rpc_server;
 use library::DBLibraryServer;
 pub use packets::DBMessage;
-pub use library::model::{MetadataTrack, Metadata};
+pub use library::model::{MetadataTrack, Metadata, Artwork, Color, Cue, CueKind};
 pub use library::database::{Track, Artist, Record};
 pub use library::database::Database;
+pub use library::analysis_cache::{default_analysis_folder, AnalysisCache};
+pub use library::history::{default_history_folder, History};
+pub use library::store::default_database_file;
@@ -97,7 +97,7 @@ impl<'a> From<Arguments<'a>> for ArgumentCollection {
 }
 
 impl DBMessage {
-    const MAGIC: [u8; 4] = [0x87, 0x23, 0x49, 0xae];
+    pub const MAGIC: [u8; 4] = [0x87, 0x23, 0x49, 0xae];
 
     pub fn new<T: Into<ArgumentCollection>>(
         transaction_id: DBField,
@@ -117,13 +117,17 @@ impl DBMessage {
     }
 
     fn transaction_id(input: &[u8]) -> IResult<&[u8], DBField> {
-        let (input, kind) = take(1u8)(input)?;
-        let (input, transaction) = take(4u8)(input)?;
+        let (rest, kind) = take(1u8)(input)?;
+        let kind = match DBFieldType::name(kind[0]) {
+            Ok(kind) => kind,
+            Err(_err) => return Err(parse_error(input, nom::error::ErrorKind::Tag)),
+        };
+        let (input, transaction) = take(4u8)(rest)?;
 
         Ok((
             input,
             DBField::new(
-                DBFieldType::name(kind[0]).unwrap(),
+                kind,
                 &Bytes::from(transaction.to_vec()),
             ),
         ))
@@ -354,6 +358,10 @@ impl StatusPacket {
     pub fn kind(&self) -> &StatusPacketType {
         &self.kind
     }
+
+    pub fn content(&self) -> &StatusContentType {
+        &self.content
+    }
 }
 
 impl From<StatusPacket> for Bytes {
@@ -524,14 +532,43 @@ impl Decode for TrackAnalyzeType {
     }
 }
 
+/// Offset of the status packet content, following the common header.
+const CDJ_CONTENT_OFFSET: usize = 0x22;
+const CDJ_PLAY_STATE_OFFSET: usize = 0x7b;
+const CDJ_FLAGS_OFFSET: usize = 0x89;
+
+const CDJ_FLAG_PLAYING: u8 = 0x40;
+const CDJ_FLAG_ON_AIR: u8 = 0x08;
+
 #[derive(Debug, PartialEq)]
 pub struct Cdj {
-    activity: u16,
-    loaded_player_number: u16,
+    activity: u8,
+    loaded_player_number: u8,
     loaded_slot: PlayerSlot,
     track_analyze_type: TrackAnalyzeType,
     track_id: u32,
     track_number: u32,
+    play_state: u8,
+    flags: u8,
+}
+
+impl Cdj {
+    /// The rekordbox id of the loaded track, if it was loaded from `player_number`'s database.
+    pub fn track_loaded_from(&self, player_number: u8) -> Option<u32> {
+        match (&self.loaded_slot, self.loaded_player_number == player_number, self.track_id) {
+            (PlayerSlot::Rekordbox, true, track_id) if track_id != 0 => Some(track_id),
+            _ => None,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.flags & CDJ_FLAG_PLAYING == CDJ_FLAG_PLAYING
+    }
+
+    /// Set by the mixer when the channel of this player is audible.
+    pub fn is_on_air(&self) -> bool {
+        self.flags & CDJ_FLAG_ON_AIR == CDJ_FLAG_ON_AIR
+    }
 }
 
 trait Decode {
@@ -544,13 +581,20 @@ impl Decode for Cdj {
     type Item = StatusContentType;
 
     fn decode(input: &[u8]) -> IResult<&[u8], Self::Item> {
-        let (input, activity) = be_u16(input)?;
-        let (input, loaded_player_number) = be_u16(input)?;
+        let (input, _length) = be_u16(input)?;
+        let (input, _player_number) = be_u8(input)?;
+        let (input, _padding) = take(2u8)(input)?;
+        let (input, activity) = be_u8(input)?;
+        let (input, loaded_player_number) = be_u8(input)?;
         let (input, loaded_slot) = PlayerSlot::decode(input)?;
         let (input, track_analyze_type) = TrackAnalyzeType::decode(input)?;
         let (input, _padding) = take(1u8)(input)?;
         let (input, track_id) = be_u32(input)?;
         let (input, track_number) = be_u32(input)?;
+        let (input, _) = take(CDJ_PLAY_STATE_OFFSET - CDJ_CONTENT_OFFSET - 0x12)(input)?;
+        let (input, play_state) = be_u8(input)?;
+        let (input, _) = take(CDJ_FLAGS_OFFSET - CDJ_PLAY_STATE_OFFSET - 1)(input)?;
+        let (input, flags) = be_u8(input)?;
 
         Ok((
             input,
@@ -561,6 +605,8 @@ impl Decode for Cdj {
                 track_analyze_type,
                 track_id,
                 track_number,
+                play_state,
+                flags,
             })
         ))
     }
@@ -12,6 +12,7 @@ use crate::rekordbox::StatusEventServer;
 use crate::rekordbox::DBLibraryServer;
 use crate::rekordbox::rpc_server;
 use crate::rekordbox::Database;
+use super::library::watcher;
 use super::keepalive::{
     Event as KeepAliveEvent,
     KeepAliveContentType,
@@ -32,6 +33,25 @@ pub enum ApplicationEvent {
     DeviceChange,
 }
 
+/// How we present ourselves to the players on the network.
+#[derive(Debug, Clone)]
+pub struct DeviceOptions {
+    pub name: String,
+    pub player_number: u8,
+    /// Only look for players on this network interface.
+    pub interface: Option<String>,
+}
+
+impl Default for DeviceOptions {
+    fn default() -> Self {
+        Self {
+            name: "Term DJ".to_string(),
+            player_number: 17,
+            interface: None,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct ServerState {
     linking: bool,
@@ -39,6 +59,7 @@ pub struct ServerState {
     linked: bool,
     address: Option<PioneerNetwork>,
     players: PlayerCollection,
+    device: DeviceOptions,
 }
 
 impl Default for ServerState {
@@ -49,6 +70,7 @@ impl Default for ServerState {
             discovery: false,
             address: None,
             players: PlayerCollection::new(),
+            device: DeviceOptions::default(),
         }
     }
 }
@@ -61,8 +83,11 @@ pub struct Server {
 }
 
 impl Server {
-    pub fn new(database: Database, tx: Sender<ApplicationEvent>) -> Self {
-        let state = Arc::new(Mutex::new(ServerState::default()));
+    pub fn new(database: Database, device: DeviceOptions, tx: Sender<ApplicationEvent>) -> Self {
+        let state = Arc::new(Mutex::new(ServerState {
+            device,
+            ..ServerState::default()
+        }));
         let database = Arc::new(database);
 
         Server {
@@ -82,12 +107,11 @@ impl Server {
             .map_err(|_| "Unable to start RPC Server".to_string());
         let db_library_future = DBLibraryServer::run(self.state.clone(), self.database.clone())
             .map_err(|_| "Unable to start DBLibraryServer".to_string());
-        match status_event_server(&self.tx, &self.state) {
-            Err(err) => {
-                dbg!(err);
-            },
-            _ => {},
-        };
+        if let Err(err) = status_event_server(&self.tx, &self.state, &self.database) {
+            eprintln!("Failed starting status event server; error = {}", err);
+        }
+        library_watcher(&self.database);
+        library_analyser(&self.database);
         dbg!("server started");
 
         tokio::spawn(async move {
@@ -131,6 +155,7 @@ impl Server {
                     }
 
                     self.broadcast_message(&address, KeepAliveStatusPackage::new(
+                        state.device.player_number,
                         address.ip(),
                         address.mac_address(),
                         4,
@@ -158,17 +183,37 @@ impl Server {
 fn status_event_server(
     tx: &Sender<ApplicationEvent>,
     state: &Arc<Mutex<ServerState>>,
+    database: &Arc<Database>,
 ) -> Result<(), &'static str> {
     let _tx = tx.clone();
-    let _state = state.clone();
+    let device = match state.lock() {
+        Ok(state) => state.device.clone(),
+        Err(_) => return Err("Failed reading device options"),
+    };
 
-    let status_event_server = StatusEventServer::bind()?;
+    let status_event_server = StatusEventServer::bind(database.clone(), device)?;
 
     thread::spawn(move || status_event_server.run());
 
     Ok(())
 }
 
+fn library_watcher(database: &Arc<Database>) {
+    let database = database.clone();
+
+    thread::spawn(move || {
+        if let Err(err) = watcher::watch(database) {
+            eprintln!("Library changes won't be picked up until restart; error = {}", err);
+        }
+    });
+}
+
+fn library_analyser(database: &Arc<Database>) {
+    let database = database.clone();
+
+    thread::spawn(move || database.analyse_tracks());
+}
+
 fn keepalive_server(tx: &Sender<ApplicationEvent>, state: &Arc<Mutex<ServerState>>) {
     let tx = tx.clone();
     let state = state.clone();
@@ -193,6 +238,7 @@ fn broadcast_sender_handler(state: &Arc<Mutex<ServerState>>) {
             if let Ok(state) = state.lock() {
                 if let Some(address) = &state.address {
                     send_broadcast_payload(&address, KeepAliveStatusPackage::new(
+                        state.device.player_number,
                         address.ip(),
                         address.mac_address(),
                         1,
@@ -236,7 +282,8 @@ fn handle_keepalive_status(
     tx: &Sender<ApplicationEvent>,
 ) {
     let previous_number_of_players = state.players.len();
-    state.synchronize_player_broadcast_network(find_interface(status.ip_addr()));
+    let network = find_interface(status.ip_addr(), state.device.interface.as_deref());
+    state.synchronize_player_broadcast_network(network);
     state.players.add_or_update(Player::new(
         event.model().to_string().clone(),
         status.player_number().to_owned(),
@@ -309,6 +356,10 @@ impl ServerState {
     pub fn address(&self) -> &Option<PioneerNetwork> {
         &self.address
     }
+
+    pub fn device(&self) -> &DeviceOptions {
+        &self.device
+    }
 }
 
 fn send_broadcast_payload<A: Into<Bytes>>(
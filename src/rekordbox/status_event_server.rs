@@ -1,45 +1,128 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::net::{UdpSocket, SocketAddr};
 use std::sync::{Arc, Mutex};
 use bytes::{Bytes};
 use std::io;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use super::packets::{
     StatusPacket,
     StatusPacketType,
     StatusContentType,
+    Cdj,
     RekordboxReply,
     LinkReply,
     Utf16FixedString,
     PlayerSlot,
 };
+use super::Database;
+use super::server::DeviceOptions;
 
-#[derive(Debug)]
 pub struct StatusEventServer {
     pub socket: Arc<Mutex<UdpSocket>>,
+    database: Arc<Database>,
+    /// Our own player number and name, tracks loaded from that player number
+    /// come from our database.
+    device: DeviceOptions,
+    plays: Mutex<PlayTracker>,
 }
 
 const STATUS_EVENT_SERVER_PORT: u16 = 50002;
 
+/// Time a track has to be audible before it counts as played, so tracks that
+/// were only previewed or cued up don't end up in the history.
+const PLAYED_THRESHOLD: Duration = Duration::from_secs(30);
+
+/// Players send their status several times per second, larger gaps mean
+/// packets were lost and aren't counted as playing time.
+const MAX_STATUS_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+struct Deck {
+    track_id: u32,
+    audible: Duration,
+    last_status: Option<Instant>,
+    recorded: bool,
+}
+
+/// Follows the on air playing time of the track loaded on each player.
+#[derive(Debug, Default)]
+struct PlayTracker {
+    decks: HashMap<u8, Deck>,
+}
+
+impl PlayTracker {
+    /// Feed a status update of `player_number`, returns the track id once the
+    /// loaded track passed the played threshold.
+    fn update(&mut self, player_number: u8, track_id: Option<u32>, audible: bool, now: Instant) -> Option<u32> {
+        let track_id = match track_id {
+            Some(track_id) => track_id,
+            None => {
+                self.decks.remove(&player_number);
+                return None;
+            },
+        };
+
+        let deck = self.decks.entry(player_number).or_insert(Deck {
+            track_id,
+            audible: Duration::from_secs(0),
+            last_status: None,
+            recorded: false,
+        });
+
+        if deck.track_id != track_id {
+            *deck = Deck {
+                track_id,
+                audible: Duration::from_secs(0),
+                last_status: None,
+                recorded: false,
+            };
+        }
+
+        if audible {
+            if let Some(elapsed) = deck.last_status.map(|last_status| now.duration_since(last_status)) {
+                if elapsed <= MAX_STATUS_INTERVAL {
+                    deck.audible += elapsed;
+                }
+            }
+            deck.last_status = Some(now);
+        } else {
+            deck.last_status = None;
+        }
+
+        if !deck.recorded && deck.audible >= PLAYED_THRESHOLD {
+            deck.recorded = true;
+            return Some(track_id);
+        }
+
+        None
+    }
+}
+
 impl StatusEventServer {
     ///
     /// Create a UdpSocket and bind it to port 50002
     /// for the StatusEventServer. This socket will both send and receive
     /// data.
-    pub fn bind() -> Result<Self, &'static str> {
+    pub fn bind(database: Arc<Database>, device: DeviceOptions) -> Result<Self, &'static str> {
         let socket = UdpSocket::bind(("0.0.0.0", STATUS_EVENT_SERVER_PORT))
             .expect("Failed to bind status event server socket");
 
         Ok(Self::new(
             Arc::new(Mutex::new(socket)),
+            database,
+            device,
         ))
     }
 
-    pub fn new(socket: Arc<Mutex<UdpSocket>>) -> StatusEventServer {
+    pub fn new(socket: Arc<Mutex<UdpSocket>>, database: Arc<Database>, device: DeviceOptions) -> StatusEventServer {
         StatusEventServer {
             socket,
+            database,
+            device,
+            plays: Mutex::new(PlayTracker::default()),
         }
     }
 
@@ -47,8 +130,29 @@ impl StatusEventServer {
         thread::sleep(Duration::from_millis(150));
     }
 
+    fn track_plays(&self, player_number: u8, cdj: &Cdj) {
+        let played = match self.plays.lock() {
+            Ok(mut plays) => plays.update(
+                player_number,
+                cdj.track_loaded_from(self.device.player_number),
+                cdj.is_playing() && cdj.is_on_air(),
+                Instant::now(),
+            ),
+            Err(_err) => None,
+        };
+
+        if let Some(track_id) = played {
+            if let Err(err) = self.database.record_play(track_id) {
+                eprintln!("Failed recording play of track {}; error = {:?}", track_id, err);
+            }
+        }
+    }
+
     fn process_packet(&self, packet: StatusPacket) -> Option<StatusPacket> {
         if packet.kind() == &StatusPacketType::Cdj {
+            if let StatusContentType::Cdj(cdj) = packet.content() {
+                self.track_plays(packet.player_number, cdj);
+            }
             return None;
         }
 
@@ -59,7 +163,7 @@ impl StatusEventServer {
                     1,
                     1,
                     StatusContentType::RekordboxReply(RekordboxReply {
-                        name: "Term DJ".to_string(),
+                        name: self.device.name.clone(),
                     })
                 ))
             },
@@ -69,15 +173,15 @@ impl StatusEventServer {
                     packet.unknown1,
                     packet.player_number,
                     StatusContentType::LinkReply(LinkReply {
-                        source_player_number: 17,
+                        source_player_number: self.device.player_number,
                         slot: PlayerSlot::Rekordbox,
-                        name: Utf16FixedString::new("Term DJ".to_string(), 64),
+                        name: Utf16FixedString::new(self.device.name.clone(), 64),
                         date: Utf16FixedString::new("".to_string(), 24),
                         unknown5: Utf16FixedString::new("".to_string(), 32),
-                        track_count: 1051,
+                        track_count: self.database.track_count(),
                         unknown6: 0,
                         unknown7: 257,
-                        playlist_count: 94,
+                        playlist_count: self.database.playlist_count(),
                         bytes_total: 0,
                         bytes_free: 0,
                     }),
@@ -149,3 +253,39 @@ impl StatusEventServer {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_records_tracks_played_on_air_past_the_threshold() {
+        let mut tracker = PlayTracker::default();
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        // Cued up but not audible
+        assert_eq!(None, tracker.update(2, Some(5), false, at(0)));
+        for second in 1..30 {
+            assert_eq!(None, tracker.update(2, Some(5), true, at(second)));
+        }
+        assert_eq!(Some(5), tracker.update(2, Some(5), true, at(31)));
+        assert_eq!(None, tracker.update(2, Some(5), true, at(32)));
+    }
+
+    #[test]
+    fn it_restarts_counting_when_another_track_is_loaded() {
+        let mut tracker = PlayTracker::default();
+        let start = Instant::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        for second in 0..20 {
+            tracker.update(1, Some(5), true, at(second));
+        }
+        for second in 20..40 {
+            assert_eq!(None, tracker.update(1, Some(6), true, at(second)));
+        }
+        // Packets lost for a minute don't count as playing time
+        assert_eq!(None, tracker.update(1, Some(6), true, at(100)));
+    }
+}
@@ -1,7 +1,27 @@
 use nom::error::ErrorKind;
+use std::path::PathBuf;
 
 pub mod network;
+pub mod text;
 
 pub fn parse_error<T>(input: T, code: ErrorKind) -> nom::Err<nom::error::Error<T>> {
     nom::Err::Error(nom::error::Error::new(input, code))
-}
\ No newline at end of file
+}
+
+/// Where TermDJ keeps its state, `$XDG_DATA_HOME/termdj` falling back to `~/.local/share/termdj`.
+pub fn data_folder() -> Option<PathBuf> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?;
+
+    Some(data_home.join("termdj"))
+}
+
+/// Where TermDJ looks for its settings, `$XDG_CONFIG_HOME/termdj` falling back to `~/.config/termdj`.
+pub fn config_folder() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+    Some(config_home.join("termdj"))
+}
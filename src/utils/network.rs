@@ -38,13 +38,20 @@ impl PioneerNetwork {
     }
 }
 
-pub fn find_interface(address: &Ipv4Addr) -> Option<PioneerNetwork> {
-    match_interface(interfaces(), &address)
+/// Find the local network `address` can be reached on, only looking at the
+/// interface named `interface` when given.
+pub fn find_interface(address: &Ipv4Addr, interface: Option<&str>) -> Option<PioneerNetwork> {
+    match_interface(interfaces(), address, interface)
 }
 
-fn match_interface(ifaces: Vec<NetworkInterface>, address: &Ipv4Addr) -> Option<PioneerNetwork> {
+fn match_interface(
+    ifaces: Vec<NetworkInterface>,
+    address: &Ipv4Addr,
+    interface: Option<&str>,
+) -> Option<PioneerNetwork> {
     ifaces
         .iter()
+        .filter(|iface| interface.is_none_or(|name| iface.name == name))
         .flat_map(|iface| {
             iface.ips.iter().filter_map(move |ip| match ip {
                 IpNetwork::V4(ip) => Some(PioneerNetwork::new(*ip, iface.mac.unwrap())),
@@ -129,7 +136,7 @@ mod tests {
         let remote_address = Ipv4Addr::new(192, 168, 10, 52);
         let local_network_address = Ipv4Network::new(Ipv4Addr::new(192, 168, 10, 50), 24).unwrap();
 
-        let network = match_interface(interfaces(), &remote_address);
+        let network = match_interface(interfaces(), &remote_address, None);
 
         assert_eq!(network.is_none(), false);
         assert_eq!(
@@ -145,16 +152,27 @@ mod tests {
     fn it_find_network_in_a_smaller_cidr() {
         let remote_address = Ipv4Addr::new(192, 168, 12, 230);
         let local_network_address = IpAddr::V4(Ipv4Addr::new(192, 168, 12, 200));
-        let network = match_interface(interfaces(), &remote_address);
+        let network = match_interface(interfaces(), &remote_address, None);
 
         assert_eq!(network.is_none(), false);
         assert_eq!(network.unwrap().ip(), local_network_address);
 
         let remote_address = Ipv4Addr::new(192, 168, 12, 24);
         let local_network_address = IpAddr::V4(Ipv4Addr::new(192, 168, 12, 50));
-        let network = match_interface(interfaces(), &remote_address);
+        let network = match_interface(interfaces(), &remote_address, None);
 
         assert_eq!(network.is_none(), false);
         assert_eq!(network.unwrap().ip(), local_network_address);
     }
+
+    #[test]
+    fn it_only_considers_the_configured_interface() {
+        let remote_address = Ipv4Addr::new(192, 168, 10, 52);
+        assert_eq!(match_interface(interfaces(), &remote_address, Some("eno1:1")), None);
+
+        let remote_address = Ipv4Addr::new(192, 168, 11, 52);
+        let network = match_interface(interfaces(), &remote_address, Some("eno1:1"));
+
+        assert_eq!(network.unwrap().ip(), Ipv4Addr::new(192, 168, 11, 50));
+    }
 }
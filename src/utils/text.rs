@@ -0,0 +1,44 @@
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+/// Fold text for comparisons that ignore case, accents and character width.
+///
+/// The compatibility decomposition maps full width forms to their ascii
+/// counterparts and splits accented letters, after which the accents are dropped.
+pub fn fold(text: &str) -> String {
+    text.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Key for sorting names alphabetically, "The Beatles" is listed under B.
+pub fn sort_key(text: &str) -> String {
+    let folded = fold(text.trim());
+
+    match folded.strip_prefix("the ") {
+        Some(rest) if !rest.trim().is_empty() => rest.trim_start().to_string(),
+        _ => folded,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_folds_case_accents_and_width() {
+        assert_eq!("beyonce", fold("Beyoncé"));
+        assert_eq!("royksopp", fold("RÖYKSOPP"));
+        assert_eq!("abc 123", fold("ＡＢＣ １２３"));
+        assert_eq!("カ", fold("ｶ"));
+    }
+
+    #[test]
+    fn it_ignores_a_leading_article_when_sorting() {
+        assert_eq!("beatles", sort_key("The Beatles"));
+        assert_eq!("the", sort_key("The"));
+        assert_eq!("theory", sort_key("Theory"));
+        assert_eq!("edith piaf", sort_key(" Édith Piaf"));
+    }
+}